@@ -0,0 +1,34 @@
+public class CGlueException extends RuntimeException {
+    private final int errorNumber;
+    private final long returnValue;
+
+    public CGlueException(String message, int errorNumber, long returnValue) {
+        super(message);
+        this.errorNumber = errorNumber;
+        this.returnValue = returnValue;
+    }
+
+    public int getErrorNumber() {
+        return errorNumber;
+    }
+
+    public long getReturnValue() {
+        return returnValue;
+    }
+
+    /**
+     * The value set to RETURN-CODE of the calling COBOL program, checked in
+     * this order: errno if the C function set it, then the returned value if
+     * it is a nonzero int, and -1 otherwise. A COBOL program therefore tests
+     * RETURN-CODE after the CALL; ON EXCEPTION is never taken.
+     */
+    public int getReturnCode() {
+        if (errorNumber != 0) {
+            return errorNumber;
+        }
+        if (returnValue != 0 && returnValue >= Integer.MIN_VALUE && returnValue <= Integer.MAX_VALUE) {
+            return (int) returnValue;
+        }
+        return -1;
+    }
+}
@@ -0,0 +1,47 @@
+import java.util.HashMap;
+import java.util.Map;
+
+/**
+ * Keeps C pointers on the Java side and hands COBOL programs integer tokens
+ * (PIC S9(9) COMP) instead. Token 0 stands for NULL. Tokens are never reused,
+ * so a released token is detected as stale. A stale token or an exhausted
+ * registry is reported with no return value, so RETURN-CODE is -1.
+ * A function whose returned pointer is a handle stores its token in one more
+ * argument after those of the C parameters, which the COBOL program must pass.
+ */
+public final class CGlueHandleRegistry {
+    private static final Map<Integer, Long> handles = new HashMap<>();
+    private static int nextToken = 1;
+
+    private CGlueHandleRegistry() {
+    }
+
+    public static synchronized int register(long pointer) {
+        if (pointer == 0) {
+            return 0;
+        }
+        if (nextToken == Integer.MAX_VALUE) {
+            throw new CGlueException("handle registry is exhausted", 0, 0);
+        }
+        int token = nextToken++;
+        handles.put(token, pointer);
+        return token;
+    }
+
+    public static synchronized long resolve(int token) {
+        if (token == 0) {
+            return 0;
+        }
+        Long pointer = handles.get(token);
+        if (pointer == null) {
+            throw new CGlueException("invalid or stale handle: " + token, 0, 0);
+        }
+        return pointer;
+    }
+
+    public static synchronized void release(int token) {
+        if (token != 0 && handles.remove(token) == null) {
+            throw new CGlueException("invalid or stale handle: " + token, 0, 0);
+        }
+    }
+}
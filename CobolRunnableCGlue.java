@@ -1,9 +1,96 @@
 import jp.osscons.opensourcecobol.libcobj.common.*;
 import jp.osscons.opensourcecobol.libcobj.call.*;
 import jp.osscons.opensourcecobol.libcobj.data.*;
+import java.io.IOException;
+import java.io.InputStream;
 import java.nio.ByteBuffer;
+import java.nio.file.Files;
+import java.nio.file.Path;
+import java.util.HashSet;
+import java.util.Set;
 
 abstract public class CobolRunnableCGlue implements CobolRunnable {
+    private static final ThreadLocal<CGlueException> lastException = new ThreadLocal<>();
+    private static final Set<String> loadedLibraries = new HashSet<>();
+
+    /**
+     * The last CGlueException caught on the current thread, or null if there
+     * has been none. Each thread that runs COBOL programs sees only its own.
+     */
+    public static CGlueException getLastException() {
+        return lastException.get();
+    }
+
+    /**
+     * Records the exception for getLastException and returns the value for
+     * RETURN-CODE, which is the only way the calling COBOL program learns of
+     * the failure: the exception is not rethrown, so CALL ... ON EXCEPTION
+     * is never triggered. See CGlueException.getReturnCode for the value.
+     */
+    int handleCGlueException(CGlueException e) {
+        lastException.set(e);
+        return e.getReturnCode();
+    }
+
+    /**
+     * Loads a glue library. A library packaged in the jar under
+     * oc4j-c-glue/native/&lt;os&gt;-&lt;arch&gt;/ is extracted to a temporary
+     * directory first; otherwise it is looked up on java.library.path.
+     */
+    static synchronized void loadLibrary(String name) {
+        if (loadedLibraries.contains(name)) {
+            return;
+        }
+        String fileName = System.mapLibraryName(name);
+        String resource = "/oc4j-c-glue/native/" + platform() + "/" + fileName;
+        try (InputStream in = CobolRunnableCGlue.class.getResourceAsStream(resource)) {
+            if (in == null) {
+                System.loadLibrary(name);
+            } else {
+                Path directory = Files.createTempDirectory("oc4j-c-glue");
+                Path file = directory.resolve(fileName);
+                Files.copy(in, file);
+                directory.toFile().deleteOnExit();
+                file.toFile().deleteOnExit();
+                System.load(file.toAbsolutePath().toString());
+            }
+            loadedLibraries.add(name);
+        } catch (IOException e) {
+            throw new UnsatisfiedLinkError("unable to extract " + resource + ": " + e);
+        }
+    }
+
+    /**
+     * The platform directory name, which matches the OS and architecture
+     * names that cobj-c-glue uses when it packages the libraries.
+     */
+    private static String platform() {
+        String os = System.getProperty("os.name").toLowerCase();
+        if (os.startsWith("linux")) {
+            os = "linux";
+        } else if (os.startsWith("mac")) {
+            os = "macos";
+        } else if (os.startsWith("windows")) {
+            os = "windows";
+        }
+        String arch = System.getProperty("os.arch");
+        switch (arch) {
+            case "amd64":
+                arch = "x86_64";
+                break;
+            case "arm64":
+                arch = "aarch64";
+                break;
+            case "i386":
+            case "i686":
+                arch = "x86";
+                break;
+            default:
+                break;
+        }
+        return os.replace(' ', '_') + "-" + arch;
+    }
+
     @Override
     public void cancel() {
     }
@@ -13,8 +100,28 @@ abstract public class CobolRunnableCGlue implements CobolRunnable {
         return false;
     }
 
+    /**
+     * Runs an opensource COBOL 4J program on behalf of a C callback.
+     * The storages share the given byte arrays, so the C trampoline sees
+     * what the program writes to its arguments.
+     */
+    static int callProgram(String className, byte[][] args) {
+        CobolRunnable program;
+        try {
+            program = (CobolRunnable) Class.forName(className).getDeclaredConstructor().newInstance();
+        } catch (ReflectiveOperationException e) {
+            throw new CGlueException("unable to load COBOL program " + className + ": " + e, 0, 0);
+        }
+        CobolDataStorage[] storages = new CobolDataStorage[args.length];
+        for (int i = 0; i < args.length; i++) {
+            storages[i] = new CobolDataStorage(args[i]);
+        }
+        return program.run(storages);
+    }
+
     byte[] storageToByteArray(CobolDataStorage storage, int size) {
-        return storage.getByteArray(0, size);
+        int available = storage.getRefOfData().length - storage.getIndex();
+        return storage.getByteArray(0, Math.min(size, available));
     }
 
     byte storageToByte(CobolDataStorage storage) {
@@ -0,0 +1,262 @@
+/// Compiler extensions that take a parenthesized argument, as in
+/// `__attribute__((nonnull))`, `__declspec(dllexport)` or `__asm__("name")`.
+static EXTENSIONS_WITH_ARGUMENTS: &[&str] = &[
+    "__attribute__",
+    "__attribute",
+    "__declspec",
+    "__asm__",
+    "__asm",
+];
+
+/// Spellings of `inline` that tree-sitter does not know.
+static INLINE_KEYWORDS: &[&str] = &["__inline__", "__inline", "__forceinline"];
+
+/// Calling conventions and keywords that do not change how a function is called from C
+/// on the platforms the glue is built for.
+static DEFAULT_EXPORT_MACROS: &[&str] = &[
+    "__stdcall",
+    "__cdecl",
+    "__fastcall",
+    "__vectorcall",
+    "__thiscall",
+    "__extension__",
+];
+
+fn is_identifier_start(c: u8) -> bool {
+    c.is_ascii_alphabetic() || c == b'_'
+}
+
+fn is_identifier_part(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'_'
+}
+
+/// The index after the comment, literal or preprocessor line starting at `index`,
+/// or None if there is none.
+fn skip_non_code(bytes: &[u8], index: usize, line_start: bool) -> Option<usize> {
+    let rest = &bytes[index..];
+    if rest.starts_with(b"//") {
+        return Some(
+            rest.iter()
+                .position(|c| *c == b'\n')
+                .map_or(bytes.len(), |p| index + p),
+        );
+    }
+    if rest.starts_with(b"/*") {
+        return Some(
+            rest[2..]
+                .windows(2)
+                .position(|w| w == b"*/")
+                .map_or(bytes.len(), |p| index + 2 + p + 2),
+        );
+    }
+    if rest[0] == b'"' || rest[0] == b'\'' {
+        let mut end = index + 1;
+        while end < bytes.len() && bytes[end] != rest[0] && bytes[end] != b'\n' {
+            end += if bytes[end] == b'\\' { 2 } else { 1 };
+        }
+        return Some((end + 1).min(bytes.len()));
+    }
+    if rest[0] == b'#' && line_start {
+        // A directive ends at the first newline that is not escaped.
+        let mut end = index;
+        while end < bytes.len() && bytes[end] != b'\n' {
+            end += if bytes[end] == b'\\' { 2 } else { 1 };
+        }
+        return Some(end.min(bytes.len()));
+    }
+    None
+}
+
+/// The index after the whitespace and comments starting at `index`.
+fn skip_blank(bytes: &[u8], mut index: usize) -> usize {
+    while index < bytes.len() {
+        if bytes[index].is_ascii_whitespace() {
+            index += 1;
+        } else if bytes[index..].starts_with(b"//") || bytes[index..].starts_with(b"/*") {
+            index = skip_non_code(bytes, index, false).unwrap_or(index + 1);
+        } else {
+            break;
+        }
+    }
+    index
+}
+
+/// The index after the balanced parentheses starting at `index`.
+fn skip_parentheses(bytes: &[u8], mut index: usize) -> usize {
+    let mut depth = 0;
+    while index < bytes.len() {
+        if let Some(next) = skip_non_code(bytes, index, false) {
+            index = next;
+            continue;
+        }
+        match bytes[index] {
+            b'(' => depth += 1,
+            b')' => depth -= 1,
+            _ => {}
+        }
+        index += 1;
+        if depth == 0 {
+            break;
+        }
+    }
+    index
+}
+
+/// Replace `bytes[start..end]` with spaces, keeping the newlines so that
+/// the positions tree-sitter reports stay the same.
+fn blank(bytes: &mut [u8], start: usize, end: usize) {
+    for c in bytes[start..end].iter_mut() {
+        if *c != b'\n' {
+            *c = b' ';
+        }
+    }
+}
+
+/// The `#define` directives of the source in order: the name, the parameter list of a
+/// function-like macro, and the body with its escaped newlines joined.
+fn macro_definitions(source_code: &str) -> Vec<(String, Option<String>, String)> {
+    let mut definitions = Vec::new();
+    let source_code = source_code.replace("\\\n", " ");
+    for line in source_code.lines() {
+        let directive = line.trim_start();
+        let rest = match directive.strip_prefix('#') {
+            Some(rest) => rest.trim_start(),
+            None => continue,
+        };
+        let rest = match rest.strip_prefix("define") {
+            Some(rest) if rest.starts_with([' ', '\t']) => rest.trim_start(),
+            _ => continue,
+        };
+        let name_end = rest
+            .bytes()
+            .position(|c| !is_identifier_part(c))
+            .unwrap_or(rest.len());
+        let (name, mut body) = rest.split_at(name_end);
+        if name.is_empty() {
+            continue;
+        }
+        let mut parameters = None;
+        if body.starts_with('(') {
+            let parameters_end = skip_parentheses(body.as_bytes(), 0);
+            parameters = Some(body[..parameters_end].to_string());
+            body = &body[parameters_end..];
+        }
+        definitions.push((name.to_string(), parameters, body.to_string()));
+    }
+    definitions
+}
+
+/// The macros the source defines as nothing but attributes or calling conventions,
+/// such as `#define API_EXPORT` or `#define API_EXPORT __declspec(dllexport)`.
+fn defined_export_macros(source_code: &str) -> Vec<String> {
+    macro_definitions(source_code)
+        .into_iter()
+        .filter(|(_, _, body)| blank_extensions(body, &[]).trim().is_empty())
+        .map(|(name, _, _)| name)
+        .collect()
+}
+
+/// The names and bodies of the object-like macros, such as `#define BUF_SIZE 256`.
+/// The directives are read as text, as tree-sitter does not parse every macro body.
+pub fn object_like_macros(source_code: &str) -> Vec<(String, String)> {
+    macro_definitions(source_code)
+        .into_iter()
+        .filter(|(_, parameters, _)| parameters.is_none())
+        .map(|(name, _, body)| (name, body))
+        .collect()
+}
+
+/// The names and parameter lists of the function-like macros, such as
+/// `#define MAX(a, b) ...`, leaving out the export macros.
+pub fn function_like_macros(source_code: &str) -> Vec<(String, String)> {
+    macro_definitions(source_code)
+        .into_iter()
+        .filter(|(_, _, body)| !blank_extensions(body, &[]).trim().is_empty())
+        .filter_map(|(name, parameters, _)| Some((name, parameters?)))
+        .collect()
+}
+
+/// Blank out what tree-sitter cannot parse in vendor headers: GCC attributes,
+/// `__declspec`, `__asm__` labels, `extern "C"` linkage blocks, and export and
+/// calling-convention macros, which may take arguments. Spellings of `inline`
+/// are replaced with the keyword. Those are the given
+/// macros and the ones the source defines as nothing but such extensions.
+pub fn strip_vendor_extensions(source_code: &str, export_macros: &[String]) -> String {
+    let mut export_macros = export_macros.to_vec();
+    export_macros.extend(defined_export_macros(source_code));
+    blank_extensions(source_code, &export_macros)
+}
+
+fn blank_extensions(source_code: &str, export_macros: &[String]) -> String {
+    let mut bytes = source_code.as_bytes().to_vec();
+    // Whether each open brace belongs to an `extern "C"` block.
+    let mut braces: Vec<bool> = Vec::new();
+    let mut line_start = true;
+    let mut index = 0;
+    while index < bytes.len() {
+        let c = bytes[index];
+        if let Some(next) = skip_non_code(&bytes, index, line_start) {
+            index = next;
+            continue;
+        }
+        if c == b'\n' {
+            line_start = true;
+            index += 1;
+            continue;
+        }
+        if c.is_ascii_whitespace() {
+            index += 1;
+            continue;
+        }
+        line_start = false;
+        if c == b'{' {
+            braces.push(false);
+            index += 1;
+            continue;
+        }
+        if c == b'}' {
+            if braces.pop() == Some(true) {
+                blank(&mut bytes, index, index + 1);
+            }
+            index += 1;
+            continue;
+        }
+        if !is_identifier_start(c) {
+            index += 1;
+            continue;
+        }
+        let start = index;
+        while index < bytes.len() && is_identifier_part(bytes[index]) {
+            index += 1;
+        }
+        let word = &source_code[start..index];
+        let after = skip_blank(&bytes, index);
+        if word == "extern" && bytes[after..].starts_with(b"\"C") {
+            let literal_end = skip_non_code(&bytes, after, false).unwrap_or(after + 1);
+            let brace = skip_blank(&bytes, literal_end);
+            if bytes.get(brace) == Some(&b'{') {
+                blank(&mut bytes, start, brace + 1);
+                braces.push(true);
+                index = brace + 1;
+            } else {
+                blank(&mut bytes, start, literal_end);
+                index = literal_end;
+            }
+        } else if INLINE_KEYWORDS.contains(&word) {
+            blank(&mut bytes, start + "inline".len(), index);
+            bytes[start..start + "inline".len()].copy_from_slice(b"inline");
+        } else if EXTENSIONS_WITH_ARGUMENTS.contains(&word)
+            || DEFAULT_EXPORT_MACROS.contains(&word)
+            || export_macros.iter().any(|m| m == word)
+        {
+            let end = if bytes.get(after) == Some(&b'(') {
+                skip_parentheses(&bytes, after)
+            } else {
+                index
+            };
+            blank(&mut bytes, start, end);
+            index = end;
+        }
+    }
+    String::from_utf8(bytes).unwrap_or_else(|_| source_code.to_string())
+}
@@ -0,0 +1,415 @@
+use crate::cfunc::CFunction;
+use crate::cobol_call::{self, ArgumentMode, CobolArgument, CobolCall};
+use crate::cobol_data::{DataDivision, Usage};
+use crate::cobol_source::Token;
+use crate::java_type::PossibleJavaType;
+use std::fmt;
+use unwrap_or::{unwrap_ok_or, unwrap_some_or};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A problem found by `check`, reported at the CALL statement.
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub file: String,
+    pub line: usize,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(
+            f,
+            "{}:{}: {}: {}",
+            self.file, self.line, severity, self.message
+        )
+    }
+}
+
+/// What the generated glue expects of a COBOL argument.
+#[derive(Clone, Copy, Debug)]
+enum Expectation {
+    /// A big-endian binary integer of the given size.
+    Integer(usize),
+    /// One byte.
+    Byte,
+    /// At least the given number of bytes, which are copied to and from C.
+    Area(usize),
+    /// Text that C reads up to a NUL or the given number of bytes.
+    Text(usize),
+}
+
+/// An argument of the generated `run` method.
+struct Slot {
+    description: String,
+    expectation: Expectation,
+    /// Whether the glue returns a value in the argument, which BY CONTENT would lose.
+    output: bool,
+}
+
+fn slots(c_function: &CFunction) -> Vec<Slot> {
+    let mut slots: Vec<Slot> = c_function
+        .parameters
+        .iter()
+        .filter(|p| p.callback.is_none())
+        .map(|p| {
+            let expectation = match p.java_type {
+                PossibleJavaType::Byte => Expectation::Byte,
+                PossibleJavaType::Short => Expectation::Integer(2),
+                PossibleJavaType::Int | PossibleJavaType::Handle | PossibleJavaType::HandleOut => {
+                    Expectation::Integer(4)
+                }
+                PossibleJavaType::ByteArray | PossibleJavaType::Callback => {
+                    Expectation::Area(p.type_size as usize)
+                }
+                PossibleJavaType::CString => Expectation::Text(p.string_length as usize + 1),
+            };
+            let output = matches!(p.java_type, PossibleJavaType::HandleOut) || p.release_handle;
+            Slot {
+                description: format!("{} {}", p.pointer_type_name(), p.var_name),
+                expectation,
+                output,
+            }
+        })
+        .collect();
+    if c_function.return_handle {
+        slots.push(Slot {
+            description: format!("the handle returned by {}", c_function.name),
+            expectation: Expectation::Integer(4),
+            output: true,
+        });
+    }
+    slots
+}
+
+/// Whether an argument is something other than a data item, e.g. a literal,
+/// `ADDRESS OF X` or `OMITTED`, which `check` does not inspect.
+fn is_data_item(argument: &CobolArgument) -> bool {
+    let first = argument.name.chars().next().unwrap_or('"');
+    !(first == '"'
+        || first == '\''
+        || first.is_ascii_digit()
+        || first == '+'
+        || first == '-'
+        || argument.name.contains('"')
+        || argument.name.contains('\'')
+        || argument.name.starts_with("ADDRESS OF ")
+        || argument.name.starts_with("LENGTH OF ")
+        || argument.name == "OMITTED")
+}
+
+fn check_argument(
+    data: &DataDivision,
+    call: &CobolCall,
+    position: usize,
+    argument: &CobolArgument,
+    slot: &Slot,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let mut report = |severity: Severity, message: String| {
+        diagnostics.push(Diagnostic {
+            file: call.file.clone(),
+            line: call.line,
+            severity,
+            message: format!(
+                "argument {} of CALL \"{}\": {}",
+                position, call.program, message
+            ),
+        })
+    };
+    if slot.output && argument.mode == ArgumentMode::Content {
+        report(
+            Severity::Warning,
+            format!(
+                "{} is passed BY CONTENT, so the program does not see the handle stored for {}",
+                argument.name, slot.description
+            ),
+        );
+    }
+    if !is_data_item(argument) {
+        return;
+    }
+    let reference = unwrap_ok_or!(data.resolve(&argument.name), e, {
+        report(Severity::Error, e);
+        return;
+    });
+    let item = &data.items[reference.index];
+    let name = format!("{} ({}:{})", argument.name, item.file, item.line);
+    let size = unwrap_some_or!(reference.size, return);
+    match slot.expectation {
+        Expectation::Integer(expected) => {
+            let usage = data.usage(reference.index);
+            if data.is_group(reference.index) {
+                report(
+                    Severity::Error,
+                    format!(
+                        "{} is a group item, but {} needs a {}-byte BINARY item",
+                        name, slot.description, expected
+                    ),
+                );
+            } else if usage != Usage::Binary && expected > 1 {
+                report(
+                    Severity::Error,
+                    format!(
+                        "{} is {}, but {} needs a {}-byte BINARY item",
+                        name,
+                        usage.name(),
+                        slot.description,
+                        expected
+                    ),
+                );
+            } else if size != expected {
+                report(
+                    Severity::Error,
+                    format!(
+                        "{} is {} bytes, but {} needs {}",
+                        name, size, slot.description, expected
+                    ),
+                );
+            }
+        }
+        Expectation::Byte => {
+            if size != 1 {
+                report(
+                    Severity::Error,
+                    format!(
+                        "{} is {} bytes, but {} needs 1",
+                        name, size, slot.description
+                    ),
+                );
+            }
+        }
+        Expectation::Area(expected) => {
+            if size < expected {
+                report(
+                    Severity::Error,
+                    format!(
+                        "{} is {} bytes, but {} needs {}",
+                        name, size, slot.description, expected
+                    ),
+                );
+            } else if size > expected {
+                report(
+                    Severity::Warning,
+                    format!(
+                        "{} is {} bytes, but only the first {} are passed to {}",
+                        name, size, expected, slot.description
+                    ),
+                );
+            }
+        }
+        Expectation::Text(expected) => {
+            if size > expected {
+                report(
+                    Severity::Warning,
+                    format!(
+                        "{} is {} bytes, but only the first {} are passed to {}",
+                        name, size, expected, slot.description
+                    ),
+                );
+            }
+        }
+    }
+}
+
+/// Split a source into its programs, each starting at its PROGRAM-ID paragraph.
+fn programs(tokens: &[Token]) -> Vec<&[Token]> {
+    let starts: Vec<usize> = (0..tokens.len())
+        .filter(|i| tokens[*i].is_word("PROGRAM-ID"))
+        .collect();
+    if starts.is_empty() {
+        return vec![tokens];
+    }
+    starts
+        .iter()
+        .enumerate()
+        .map(|(n, start)| &tokens[*start..starts.get(n + 1).copied().unwrap_or(tokens.len())])
+        .collect()
+}
+
+/// Check the CALL statements of a COBOL source that call one of `c_functions`
+/// against the parameters the generated glue expects.
+pub fn check_calls(tokens: &[Token], c_functions: &[CFunction]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for program in programs(tokens) {
+        let data = DataDivision::parse(program);
+        for call in cobol_call::scan_calls(program).iter() {
+            let c_function = unwrap_some_or!(
+                c_functions.iter().find(|f| f.name == call.program),
+                continue
+            );
+            let slots = slots(c_function);
+            if call.arguments.len() != slots.len() {
+                diagnostics.push(Diagnostic {
+                    file: call.file.clone(),
+                    line: call.line,
+                    severity: Severity::Error,
+                    message: format!(
+                        "CALL \"{}\" passes {} argument(s), but the glue takes {}",
+                        call.program,
+                        call.arguments.len(),
+                        slots.len()
+                    ),
+                });
+            }
+            for (position, (argument, slot)) in call.arguments.iter().zip(slots.iter()).enumerate()
+            {
+                check_argument(&data, call, position + 1, argument, slot, &mut diagnostics);
+            }
+        }
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cobol_source::{source_tokens, SourceFormat};
+    use crate::cparam::CParameter;
+
+    /// `int fill(int count, struct point *p, char *name, db **out)` with `db` a handle type.
+    fn fill_function() -> CFunction {
+        let mut c_function = CFunction::new();
+        c_function.name = "fill".to_string();
+        c_function.return_type = "int".to_string();
+        c_function
+            .parameters
+            .push(CParameter::new("count", "int", 0, 4));
+        c_function
+            .parameters
+            .push(CParameter::new("p", "struct point", 1, 8));
+        let mut name = CParameter::new("name", "char", 1, 1);
+        name.set_string(10);
+        c_function.parameters.push(name);
+        let mut out = CParameter::new("out", "db", 2, 8);
+        out.set_handle(false);
+        c_function.parameters.push(out);
+        c_function
+    }
+
+    fn check(procedure: &str) -> Vec<Diagnostic> {
+        let source = format!(
+            "IDENTIFICATION DIVISION.
+             PROGRAM-ID. MAIN.
+             DATA DIVISION.
+             WORKING-STORAGE SECTION.
+             01 CNT PIC S9(9) USAGE BINARY.
+             01 CNT-DISPLAY PIC S9(9).
+             01 CNT-SHORT PIC S9(4) USAGE BINARY.
+             01 PT.
+                05 PT-X PIC S9(9) COMP-5.
+                05 PT-Y PIC S9(9) COMP-5.
+             01 BIG-PT PIC X(12).
+             01 SMALL-PT PIC X(4).
+             01 NAME PIC X(10).
+             01 LONG-NAME PIC X(20).
+             01 HANDLE PIC S9(9) COMP.
+             PROCEDURE DIVISION.
+             {}",
+            procedure
+        );
+        check_calls(
+            &source_tokens(&source, "main.cbl", SourceFormat::Free),
+            &[fill_function()],
+        )
+    }
+
+    fn messages(diagnostics: &[Diagnostic]) -> Vec<(Severity, String)> {
+        diagnostics
+            .iter()
+            .map(|d| (d.severity, d.message.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn matching_call_has_no_diagnostics() {
+        assert!(check("CALL 'fill' USING CNT PT NAME HANDLE.").is_empty());
+    }
+
+    #[test]
+    fn calls_of_other_programs_are_not_checked() {
+        assert!(check("CALL 'other' USING CNT-DISPLAY.").is_empty());
+    }
+
+    #[test]
+    fn argument_count_is_checked() {
+        let diagnostics = check("CALL 'fill' USING CNT PT.");
+        assert_eq!(
+            messages(&diagnostics),
+            vec![(
+                Severity::Error,
+                "CALL \"fill\" passes 2 argument(s), but the glue takes 4".to_string()
+            )]
+        );
+        assert_eq!(diagnostics[0].file, "main.cbl");
+    }
+
+    #[test]
+    fn integer_arguments_must_be_binary_of_the_right_size() {
+        let diagnostics = check("CALL 'fill' USING CNT-DISPLAY PT NAME HANDLE.");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert!(diagnostics[0]
+            .message
+            .contains("is DISPLAY, but int count needs a 4-byte BINARY item"));
+
+        let diagnostics = check("CALL 'fill' USING CNT-SHORT PT NAME HANDLE.");
+        assert!(diagnostics[0]
+            .message
+            .contains("is 2 bytes, but int count needs 4"));
+
+        let diagnostics = check("CALL 'fill' USING PT PT NAME HANDLE.");
+        assert!(diagnostics[0].message.contains("is a group item"));
+    }
+
+    #[test]
+    fn areas_and_text_are_checked_by_size() {
+        let diagnostics = check("CALL 'fill' USING CNT SMALL-PT LONG-NAME HANDLE.");
+        assert_eq!(
+            messages(&diagnostics)
+                .iter()
+                .map(|(severity, _)| *severity)
+                .collect::<Vec<_>>(),
+            vec![Severity::Error, Severity::Warning]
+        );
+        assert!(diagnostics[0]
+            .message
+            .contains("is 4 bytes, but struct point* p needs 8"));
+        assert!(diagnostics[1]
+            .message
+            .contains("is 20 bytes, but only the first 11 are passed"));
+
+        let diagnostics = check("CALL 'fill' USING CNT BIG-PT NAME HANDLE.");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn output_handle_by_content_is_warned_about() {
+        let diagnostics = check("CALL 'fill' USING CNT PT NAME BY CONTENT HANDLE.");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert!(diagnostics[0].message.contains("BY CONTENT"));
+    }
+
+    #[test]
+    fn undefined_items_and_literals() {
+        let diagnostics = check("CALL 'fill' USING NOPE PT 'text' HANDLE.");
+        assert_eq!(
+            messages(&diagnostics),
+            vec![(
+                Severity::Error,
+                "argument 1 of CALL \"fill\": NOPE is not defined".to_string()
+            )]
+        );
+    }
+}
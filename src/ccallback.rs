@@ -0,0 +1,37 @@
+use crate::cparam::CParameter;
+
+/// The signature of a function pointer parameter and the opensource COBOL 4J
+/// program (a `CobolRunnable` class name) that the generated trampoline runs.
+#[derive(Clone, Debug)]
+pub struct CCallback {
+    pub program: String,
+    pub return_type: String,
+    pub parameters: Vec<CParameter>,
+}
+
+impl CCallback {
+    pub fn new() -> Self {
+        Self {
+            program: String::new(),
+            return_type: String::new(),
+            parameters: Vec::new(),
+        }
+    }
+
+    pub fn pointer_type_name(&self) -> String {
+        let parameter_types: Vec<String> = self
+            .parameters
+            .iter()
+            .map(|p| p.pointer_type_name())
+            .collect();
+        if parameter_types.is_empty() {
+            format!("{} (*)(void)", self.return_type)
+        } else {
+            format!("{} (*)({})", self.return_type, parameter_types.join(", "))
+        }
+    }
+
+    pub fn trampoline_name(function_name: &str, var_name: &str) -> String {
+        format!("oc4j_glue_callback_{}_{}", function_name, var_name)
+    }
+}
@@ -0,0 +1,205 @@
+/// The type of a constant macro, which decides how the info program prints it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConstantKind {
+    Integer,
+    Real,
+    String,
+}
+
+/// The value of a constant macro as the C compiler evaluated it.
+#[derive(Clone, Debug)]
+pub enum ConstantValue {
+    Integer(i128),
+    Real(f64),
+    String(String),
+}
+
+/// An object-like macro whose value is a constant expression, e.g. `#define BUF_SIZE 256`.
+#[derive(Clone, Debug)]
+pub struct CConstant {
+    pub name: String,
+    pub value: ConstantValue,
+}
+
+/// Type names that may appear in casts, e.g. `((unsigned long)1 << 40)`.
+static CAST_TYPE_NAMES: &[&str] = &["char", "short", "int", "long", "signed", "unsigned"];
+
+#[derive(Clone, Debug, PartialEq)]
+enum MacroToken {
+    Integer,
+    Real,
+    String,
+    Identifier(String),
+    Punctuator(char),
+}
+
+/// Split a macro body into tokens, or None if it has something other than literals,
+/// identifiers and operators, such as braces or a wide string.
+fn tokenize(body: &str) -> Option<Vec<MacroToken>> {
+    let chars: Vec<char> = body.chars().collect();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+    while index < chars.len() {
+        let c = chars[index];
+        if c.is_whitespace() {
+            index += 1;
+        } else if c == '/' && chars.get(index + 1) == Some(&'/') {
+            break;
+        } else if c == '/' && chars.get(index + 1) == Some(&'*') {
+            index += 2;
+            while index < chars.len()
+                && !(chars[index] == '*' && chars.get(index + 1) == Some(&'/'))
+            {
+                index += 1;
+            }
+            index += 2;
+        } else if c.is_ascii_digit()
+            || (c == '.' && chars.get(index + 1).is_some_and(|c| c.is_ascii_digit()))
+        {
+            let start = index;
+            while index < chars.len() {
+                let c = chars[index];
+                let hex = chars[start..index].starts_with(&['0', 'x'])
+                    || chars[start..index].starts_with(&['0', 'X']);
+                let exponent_sign = (c == '+' || c == '-')
+                    && if hex {
+                        matches!(chars[index - 1], 'p' | 'P')
+                    } else {
+                        matches!(chars[index - 1], 'e' | 'E')
+                    };
+                if c.is_ascii_alphanumeric() || c == '.' || c == '_' || exponent_sign {
+                    index += 1;
+                } else {
+                    break;
+                }
+            }
+            let number: String = chars[start..index]
+                .iter()
+                .collect::<String>()
+                .to_lowercase();
+            let real = if number.starts_with("0x") {
+                number.contains('p')
+            } else {
+                number.contains('.') || number.contains('e') || number.ends_with('f')
+            };
+            tokens.push(if real {
+                MacroToken::Real
+            } else {
+                MacroToken::Integer
+            });
+        } else if c == '"' || c == '\'' {
+            index += 1;
+            while index < chars.len() && chars[index] != c {
+                index += if chars[index] == '\\' { 2 } else { 1 };
+            }
+            if index >= chars.len() {
+                return None;
+            }
+            index += 1;
+            tokens.push(if c == '"' {
+                MacroToken::String
+            } else {
+                MacroToken::Integer
+            });
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let start = index;
+            while index < chars.len()
+                && (chars[index].is_ascii_alphanumeric() || chars[index] == '_')
+            {
+                index += 1;
+            }
+            // A prefixed literal such as L"..." or u8"..." is not a plain C string.
+            if chars.get(index).is_some_and(|c| *c == '"' || *c == '\'') {
+                return None;
+            }
+            tokens.push(MacroToken::Identifier(chars[start..index].iter().collect()));
+        } else if "+-*/%<>=!&|^~?:()".contains(c) {
+            index += 1;
+            tokens.push(MacroToken::Punctuator(c));
+        } else {
+            return None;
+        }
+    }
+    Some(tokens)
+}
+
+/// The kind of the macro `name`, or None if its value is not a constant.
+fn classify(
+    name: &str,
+    definitions: &[(String, String)],
+    enum_constants: &[String],
+    kinds: &mut Vec<(String, Option<ConstantKind>)>,
+) -> Option<ConstantKind> {
+    if let Some((_, kind)) = kinds.iter().find(|(n, _)| n == name) {
+        return *kind;
+    }
+    // A macro that refers to itself is not a constant.
+    kinds.push((name.to_string(), None));
+    let body = &definitions.iter().rev().find(|(n, _)| n == name)?.1;
+    let kind = (|| {
+        let tokens = tokenize(body)?;
+        let mut kind = None;
+        let mut has_operand = false;
+        for token in tokens.iter() {
+            let operand_kind = match token {
+                MacroToken::Integer => ConstantKind::Integer,
+                MacroToken::Real => ConstantKind::Real,
+                MacroToken::String => ConstantKind::String,
+                MacroToken::Identifier(identifier) => {
+                    if CAST_TYPE_NAMES.contains(&identifier.as_str()) {
+                        continue;
+                    } else if identifier == "float" || identifier == "double" {
+                        ConstantKind::Real
+                    } else if enum_constants.contains(identifier) {
+                        ConstantKind::Integer
+                    } else {
+                        classify(identifier, definitions, enum_constants, kinds)?
+                    }
+                }
+                MacroToken::Punctuator(_) => continue,
+            };
+            has_operand = true;
+            kind = match (kind, operand_kind) {
+                (None, k) => Some(k),
+                (Some(ConstantKind::String), ConstantKind::String) => Some(ConstantKind::String),
+                (Some(ConstantKind::String), _) | (Some(_), ConstantKind::String) => return None,
+                (Some(ConstantKind::Real), _) | (Some(_), ConstantKind::Real) => {
+                    Some(ConstantKind::Real)
+                }
+                (Some(k), _) => Some(k),
+            };
+        }
+        // Strings are only concatenated, so no operator may come with them.
+        let operator = tokens
+            .iter()
+            .any(|t| matches!(t, MacroToken::Punctuator(c) if *c != '(' && *c != ')'));
+        if !has_operand || (kind == Some(ConstantKind::String) && operator) {
+            return None;
+        }
+        kind
+    })();
+    if let Some(entry) = kinds.iter_mut().find(|(n, _)| n == name) {
+        entry.1 = kind;
+    }
+    kind
+}
+
+/// The object-like macros of `definitions` (name and body, in source order) whose values
+/// are integer, floating-point or string constant expressions. They may refer to
+/// other such macros and to the enum constants.
+pub fn constant_macros(
+    definitions: &[(String, String)],
+    enum_constants: &[String],
+) -> Vec<(String, ConstantKind)> {
+    let mut kinds = Vec::new();
+    let mut constants = Vec::new();
+    for (name, _) in definitions.iter() {
+        if constants.iter().any(|(n, _)| n == name) {
+            continue;
+        }
+        if let Some(kind) = classify(name, definitions, enum_constants, &mut kinds) {
+            constants.push((name.clone(), kind));
+        }
+    }
+    constants
+}
@@ -0,0 +1,24 @@
+/// An enum constant. Its value is resolved by the C compiler when the info program runs.
+#[derive(Clone, Debug)]
+pub struct CEnumConstant {
+    pub name: String,
+    pub value: i64,
+}
+
+/// An enum definition of the C header.
+#[derive(Clone, Debug)]
+pub struct CEnum {
+    /// The names that refer to the enum type, e.g. `enum status` and `status_t`.
+    /// An anonymous enum that is not typedef'd has none, but its constants are kept.
+    pub type_names: Vec<String>,
+    pub constants: Vec<CEnumConstant>,
+}
+
+impl CEnum {
+    pub fn new() -> Self {
+        Self {
+            type_names: Vec::new(),
+            constants: Vec::new(),
+        }
+    }
+}
@@ -1,10 +1,42 @@
-use crate::cparam::CParameter;
+use crate::cparam::{CParameter, TYPE_QUALIFIERS};
+
+/// A return value of a C function that is reported to Java as a `CGlueException`.
+#[derive(Clone, Debug)]
+pub enum ErrorValue {
+    Negative,
+    Null,
+    Value(i64),
+}
+
+/// How the header provides a function. Static inline functions and function-like
+/// macros have no symbol in the C library, so the glue defines a wrapper that calls them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FunctionKind {
+    Extern,
+    Inline,
+    Macro,
+}
 
 #[derive(Clone, Debug)]
 pub struct CFunction {
     pub return_type: String,
     pub name: String,
     pub parameters: Vec<CParameter>,
+    pub error_values: Vec<ErrorValue>,
+    pub check_errno: bool,
+    /// Whether the returned pointer is registered as a handle. Its token is stored
+    /// in one more COBOL argument after those of the parameters, a `PIC S9(9) COMP`
+    /// item, which the caller must pass.
+    pub return_handle: bool,
+    /// Whether the return type is an enum, which Java sees as an `int`.
+    pub return_enum: bool,
+    /// Whether the C function takes `...` after its first `num_of_fixed_parameters` parameters.
+    pub variadic: bool,
+    pub num_of_fixed_parameters: usize,
+    /// The variadic C function that a fixed-arity instance calls. Instances are
+    /// named in the schema, and their parameters include the variadic arguments.
+    pub instance_of: Option<String>,
+    pub kind: FunctionKind,
 }
 
 impl CFunction {
@@ -13,6 +45,100 @@ impl CFunction {
             return_type: String::new(),
             name: String::new(),
             parameters: Vec::new(),
+            error_values: Vec::new(),
+            check_errno: false,
+            return_handle: false,
+            return_enum: false,
+            variadic: false,
+            num_of_fixed_parameters: 0,
+            instance_of: None,
+            kind: FunctionKind::Extern,
+        }
+    }
+
+    /// The name of the C function that the glue calls.
+    pub fn c_name(&self) -> &str {
+        self.instance_of.as_deref().unwrap_or(&self.name)
+    }
+
+    /// Whether the glue calls the function through a wrapper of its own.
+    pub fn needs_wrapper(&self) -> bool {
+        self.kind != FunctionKind::Extern
+    }
+
+    /// The name of the wrapper that calls an inline function or a macro.
+    pub fn wrapper_name(&self) -> String {
+        format!("oc4j_glue_wrap_{}", self.name)
+    }
+
+    /// The index of the first variadic argument, if the C function is variadic.
+    pub fn first_variadic_index(&self) -> Option<usize> {
+        if self.variadic {
+            Some(self.num_of_fixed_parameters)
+        } else {
+            None
+        }
+    }
+
+    /// The return type without its qualifiers, e.g. `char *` for `const char *`.
+    pub fn unqualified_return_type(&self) -> String {
+        self.return_type
+            .split_whitespace()
+            .filter(|w| !TYPE_QUALIFIERS.contains(w))
+            .collect::<Vec<&str>>()
+            .join(" ")
+    }
+
+    /// The return type that decides the Java type of the result.
+    pub fn java_return_type_name(&self) -> &str {
+        if self.return_enum {
+            "int"
+        } else {
+            &self.return_type
         }
     }
+
+    pub fn returns_pointer(&self) -> bool {
+        self.return_type.ends_with('*')
+    }
+
+    pub fn returns_void(&self) -> bool {
+        self.return_type == "void"
+    }
+
+    pub fn has_callback(&self) -> bool {
+        self.parameters.iter().any(|p| p.callback.is_some())
+    }
+
+    /// The index in `argStorages` of each parameter. Callback parameters take
+    /// no COBOL argument, so they share the index of the next parameter.
+    pub fn storage_indices(&self) -> Vec<usize> {
+        let mut index = 0;
+        let mut indices = Vec::new();
+        for parameter in self.parameters.iter() {
+            indices.push(index);
+            if parameter.callback.is_none() {
+                index += 1;
+            }
+        }
+        indices
+    }
+
+    /// The number of COBOL arguments that correspond to C parameters.
+    pub fn num_of_arguments(&self) -> usize {
+        self.parameters
+            .iter()
+            .filter(|p| p.callback.is_none())
+            .count()
+    }
+
+    /// Whether the generated glue has to keep the value returned by the C function.
+    pub fn uses_return_value(&self) -> bool {
+        !self.returns_void() && (self.has_error_check() || self.return_handle)
+    }
+
+    /// Whether the generated glue has to inspect the result of the call.
+    pub fn has_error_check(&self) -> bool {
+        !self.error_values.is_empty() || self.check_errno
+    }
 }
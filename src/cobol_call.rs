@@ -0,0 +1,306 @@
+use crate::cobol_source::{Token, TokenKind};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArgumentMode {
+    Reference,
+    Content,
+    Value,
+}
+
+impl ArgumentMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ArgumentMode::Reference => "reference",
+            ArgumentMode::Content => "content",
+            ArgumentMode::Value => "value",
+        }
+    }
+}
+
+/// An argument in the USING phrase of a CALL statement.
+/// `name` is the data item as written, e.g. `A OF B(1)`, `ADDRESS OF X` or a literal.
+#[derive(Clone, Debug)]
+pub struct CobolArgument {
+    pub name: String,
+    pub mode: ArgumentMode,
+}
+
+/// A CALL statement whose target is a literal.
+#[derive(Clone, Debug)]
+pub struct CobolCall {
+    pub program: String,
+    pub file: String,
+    pub line: usize,
+    pub arguments: Vec<CobolArgument>,
+    pub returning: Option<String>,
+}
+
+/// Words that end the USING phrase of a CALL statement.
+static CALL_END_WORDS: &[&str] = &[
+    "RETURNING",
+    "GIVING",
+    "ON",
+    "EXCEPTION",
+    "OVERFLOW",
+    "NOT",
+    "END-CALL",
+    "ACCEPT",
+    "ADD",
+    "ALLOCATE",
+    "ALTER",
+    "CALL",
+    "CANCEL",
+    "CLOSE",
+    "COMPUTE",
+    "CONTINUE",
+    "DELETE",
+    "DISPLAY",
+    "DIVIDE",
+    "ELSE",
+    "END-EVALUATE",
+    "END-IF",
+    "END-PERFORM",
+    "ENTRY",
+    "EVALUATE",
+    "EXIT",
+    "FREE",
+    "GO",
+    "GOBACK",
+    "IF",
+    "INITIALIZE",
+    "INSPECT",
+    "MOVE",
+    "MULTIPLY",
+    "OPEN",
+    "PERFORM",
+    "READ",
+    "RELEASE",
+    "RETURN",
+    "REWRITE",
+    "SEARCH",
+    "SET",
+    "SORT",
+    "START",
+    "STOP",
+    "STRING",
+    "SUBTRACT",
+    "UNSTRING",
+    "WHEN",
+    "WRITE",
+];
+
+fn ends_call(token: &Token) -> bool {
+    token.kind == TokenKind::Period
+        || (token.kind == TokenKind::Word && CALL_END_WORDS.contains(&token.text.as_str()))
+}
+
+/// Read a data item reference starting at `index`: a literal, `ADDRESS OF`/`LENGTH OF`,
+/// or an identifier with its qualifiers and subscripts. Returns the text and the next index.
+fn read_item(tokens: &[Token], mut index: usize) -> (String, usize) {
+    let mut text = Vec::new();
+    while index < tokens.len()
+        && (tokens[index].is_word("ADDRESS") || tokens[index].is_word("LENGTH"))
+        && tokens.get(index + 1).is_some_and(|t| t.is_word("OF"))
+    {
+        text.push(format!("{} OF", tokens[index].text));
+        index += 2;
+    }
+    if let Some(token) = tokens.get(index) {
+        text.push(token.text.clone());
+        index += 1;
+    }
+    loop {
+        match tokens.get(index) {
+            Some(t) if (t.is_word("OF") || t.is_word("IN")) && index + 1 < tokens.len() => {
+                text.push(format!("{} {}", t.text, tokens[index + 1].text));
+                index += 2;
+            }
+            Some(t) if t.kind == TokenKind::LeftParen => {
+                let mut depth = 0;
+                let mut subscript = String::new();
+                while index < tokens.len() {
+                    let t = &tokens[index];
+                    match t.kind {
+                        TokenKind::LeftParen => depth += 1,
+                        TokenKind::RightParen => depth -= 1,
+                        _ => {
+                            if !subscript.ends_with('(') && !subscript.is_empty() {
+                                subscript.push(' ');
+                            }
+                        }
+                    }
+                    subscript += &t.text;
+                    index += 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                let last = text.pop().unwrap_or_default();
+                text.push(format!("{}{}", last, subscript));
+            }
+            _ => break,
+        }
+    }
+    (text.join(" "), index)
+}
+
+/// Collect the CALL statements with a literal target. CALLs of a data item
+/// cannot be resolved statically and are reported on stderr.
+pub fn scan_calls(tokens: &[Token]) -> Vec<CobolCall> {
+    let mut calls = Vec::new();
+    let mut index = 0;
+    while index < tokens.len() {
+        if !tokens[index].is_word("CALL") {
+            index += 1;
+            continue;
+        }
+        let call_token = &tokens[index];
+        index += 1;
+        let target = match tokens.get(index) {
+            Some(t) => t,
+            None => break,
+        };
+        if target.kind != TokenKind::Literal {
+            eprintln!(
+                "{}:{}: skipping CALL {}, whose target is not a literal",
+                call_token.file, call_token.line, target.text
+            );
+            continue;
+        }
+        index += 1;
+        let mut call = CobolCall {
+            program: target.literal_value(),
+            file: call_token.file.clone(),
+            line: call_token.line,
+            arguments: Vec::new(),
+            returning: None,
+        };
+        if tokens.get(index).is_some_and(|t| t.is_word("USING")) {
+            index += 1;
+            let mut mode = ArgumentMode::Reference;
+            while index < tokens.len() && !ends_call(&tokens[index]) {
+                let token = &tokens[index];
+                if token.kind == TokenKind::Word {
+                    match token.text.as_str() {
+                        "BY" => {
+                            index += 1;
+                            continue;
+                        }
+                        "REFERENCE" => {
+                            mode = ArgumentMode::Reference;
+                            index += 1;
+                            continue;
+                        }
+                        "CONTENT" => {
+                            mode = ArgumentMode::Content;
+                            index += 1;
+                            continue;
+                        }
+                        "VALUE" => {
+                            mode = ArgumentMode::Value;
+                            index += 1;
+                            continue;
+                        }
+                        "UNSIGNED" | "AUTO" | "DEFAULT" => {
+                            index += 1;
+                            continue;
+                        }
+                        "SIZE" => {
+                            // BY VALUE [UNSIGNED] SIZE [IS] {AUTO | DEFAULT | n}
+                            index += 1;
+                            if tokens.get(index).is_some_and(|t| t.is_word("IS")) {
+                                index += 1;
+                            }
+                            index += 1;
+                            continue;
+                        }
+                        _ => {}
+                    }
+                }
+                let (name, next) = read_item(tokens, index);
+                call.arguments.push(CobolArgument { name, mode });
+                index = next;
+            }
+        }
+        if tokens
+            .get(index)
+            .is_some_and(|t| t.is_word("RETURNING") || t.is_word("GIVING"))
+        {
+            let (name, next) = read_item(tokens, index + 1);
+            call.returning = Some(name);
+            index = next;
+        }
+        calls.push(call);
+    }
+    calls
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cobol_source::{source_tokens, SourceFormat};
+
+    fn calls(source: &str) -> Vec<CobolCall> {
+        scan_calls(&source_tokens(source, "test.cbl", SourceFormat::Free))
+    }
+
+    #[test]
+    fn scan_calls_reads_modes_and_items() {
+        let calls = calls(
+            "PROCEDURE DIVISION.
+             CALL \"fill\" USING A OF B(1) BY CONTENT C
+                 BY VALUE SIZE IS 4 D ADDRESS OF E 'lit'
+                 RETURNING R
+             END-CALL.
+             CALL 'other'.
+             DISPLAY 'done'.",
+        );
+        assert_eq!(calls.len(), 2);
+        let call = &calls[0];
+        assert_eq!(call.program, "fill");
+        assert_eq!(call.line, 2);
+        let arguments: Vec<(&str, ArgumentMode)> = call
+            .arguments
+            .iter()
+            .map(|a| (a.name.as_str(), a.mode))
+            .collect();
+        assert_eq!(
+            arguments,
+            vec![
+                ("A OF B(1)", ArgumentMode::Reference),
+                ("C", ArgumentMode::Content),
+                ("D", ArgumentMode::Value),
+                ("ADDRESS OF E", ArgumentMode::Value),
+                ("'lit'", ArgumentMode::Value),
+            ]
+        );
+        assert_eq!(call.returning.as_deref(), Some("R"));
+        assert_eq!(calls[1].program, "other");
+        assert!(calls[1].arguments.is_empty());
+    }
+
+    #[test]
+    fn scan_calls_stops_at_next_statement() {
+        let calls = calls(
+            "PROCEDURE DIVISION.
+             IF X = 1
+                 CALL 'a' USING X
+                 MOVE 1 TO Y
+             ELSE
+                 CALL 'b' USING Y ON EXCEPTION CONTINUE
+             END-IF.",
+        );
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].arguments.len(), 1);
+        assert_eq!(calls[0].arguments[0].name, "X");
+        assert_eq!(calls[1].program, "b");
+        assert_eq!(calls[1].arguments.len(), 1);
+    }
+
+    #[test]
+    fn scan_calls_skips_dynamic_targets() {
+        let calls = calls("PROCEDURE DIVISION.\nCALL PROG-NAME USING X.\nCALL 'c'.");
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].program, "c");
+    }
+}
@@ -0,0 +1,474 @@
+use crate::cobol_source::{Token, TokenKind};
+use unwrap_or::{unwrap_ok_or, unwrap_some_or};
+
+/// How an elementary item is stored.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Usage {
+    Display,
+    National,
+    /// BINARY, COMP and COMP-4, which are stored big-endian.
+    Binary,
+    /// COMP-5, INDEX and BINARY-CHAR/SHORT/LONG/DOUBLE, which are stored in the
+    /// native byte order. The size is fixed unless it follows from the PICTURE.
+    NativeBinary(Option<usize>),
+    PackedDecimal,
+    Float(usize),
+    Pointer,
+}
+
+impl Usage {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Usage::Display => "DISPLAY",
+            Usage::National => "NATIONAL",
+            Usage::Binary => "BINARY",
+            Usage::NativeBinary(_) => "native binary (COMP-5)",
+            Usage::PackedDecimal => "PACKED-DECIMAL",
+            Usage::Float(_) => "floating-point",
+            Usage::Pointer => "POINTER",
+        }
+    }
+
+    fn from_word(word: &str) -> Option<Usage> {
+        Some(match word {
+            "DISPLAY" => Usage::Display,
+            "NATIONAL" => Usage::National,
+            "BINARY" | "COMP" | "COMPUTATIONAL" | "COMP-4" | "COMPUTATIONAL-4" => Usage::Binary,
+            "COMP-5" | "COMPUTATIONAL-5" | "COMP-X" | "COMPUTATIONAL-X" => {
+                Usage::NativeBinary(None)
+            }
+            "BINARY-CHAR" => Usage::NativeBinary(Some(1)),
+            "BINARY-SHORT" => Usage::NativeBinary(Some(2)),
+            "BINARY-LONG" | "INDEX" => Usage::NativeBinary(Some(4)),
+            "BINARY-DOUBLE" | "BINARY-C-LONG" => Usage::NativeBinary(Some(8)),
+            "COMP-3" | "COMPUTATIONAL-3" | "PACKED-DECIMAL" => Usage::PackedDecimal,
+            "COMP-1" | "COMPUTATIONAL-1" | "FLOAT-SHORT" => Usage::Float(4),
+            "COMP-2" | "COMPUTATIONAL-2" | "FLOAT-LONG" => Usage::Float(8),
+            "POINTER" | "PROGRAM-POINTER" | "PROCEDURE-POINTER" | "FUNCTION-POINTER" => {
+                Usage::Pointer
+            }
+            _ => return None,
+        })
+    }
+}
+
+/// Words that start a clause of a data description entry, so they are not the data name.
+static CLAUSE_WORDS: &[&str] = &[
+    "PIC",
+    "PICTURE",
+    "USAGE",
+    "OCCURS",
+    "REDEFINES",
+    "VALUE",
+    "VALUES",
+    "SIGN",
+    "SYNC",
+    "SYNCHRONIZED",
+    "JUST",
+    "JUSTIFIED",
+    "BLANK",
+    "EXTERNAL",
+    "GLOBAL",
+    "BASED",
+];
+
+/// A data description entry of the DATA DIVISION.
+#[derive(Clone, Debug)]
+pub struct DataItem {
+    pub level: u32,
+    pub name: String,
+    pub parent: Option<usize>,
+    pub picture: Option<String>,
+    pub usage: Option<Usage>,
+    pub occurs: usize,
+    pub redefines: bool,
+    pub sign_separate: bool,
+    pub file: String,
+    pub line: usize,
+}
+
+/// A data item referenced in a CALL statement.
+#[derive(Clone, Copy, Debug)]
+pub struct Reference {
+    pub index: usize,
+    /// The number of bytes passed, if it is known.
+    pub size: Option<usize>,
+}
+
+/// The data items of one program.
+#[derive(Clone, Debug)]
+pub struct DataDivision {
+    pub items: Vec<DataItem>,
+}
+
+/// Whether `text` only has characters that may appear in a PICTURE character-string.
+fn is_picture_part(text: &str) -> bool {
+    !text.is_empty() && text.chars().all(|c| "AXNZ9SVPBE0/,.+-*$CRD".contains(c))
+}
+
+/// Read the PICTURE character-string starting at `index`. It may have been split into
+/// several tokens by the repetition factors, e.g. `S9(5)V9(2)`.
+fn read_picture(tokens: &[Token], mut index: usize) -> (String, usize) {
+    let mut picture = String::new();
+    while let Some(token) = tokens.get(index) {
+        match token.kind {
+            TokenKind::Word if picture.is_empty() || picture.ends_with(')') => {
+                if !picture.is_empty() && !is_picture_part(&token.text) {
+                    break;
+                }
+                picture += &token.text;
+                index += 1;
+            }
+            TokenKind::LeftParen
+                if tokens
+                    .get(index + 2)
+                    .is_some_and(|t| t.kind == TokenKind::RightParen) =>
+            {
+                picture += &format!("({})", tokens[index + 1].text);
+                index += 3;
+            }
+            _ => break,
+        }
+    }
+    (picture, index)
+}
+
+/// The number of bytes a DISPLAY item of `picture` takes, the number of its
+/// digit positions and whether it is signed.
+fn picture_positions(picture: &str) -> (usize, usize, bool) {
+    let chars: Vec<char> = picture.chars().collect();
+    let (mut size, mut digits, mut signed) = (0, 0, false);
+    let mut index = 0;
+    while index < chars.len() {
+        let c = chars[index];
+        index += 1;
+        let mut count = 1;
+        if chars.get(index) == Some(&'(') {
+            let end = chars[index..]
+                .iter()
+                .position(|c| *c == ')')
+                .map_or(chars.len(), |p| index + p);
+            count = chars[index + 1..end]
+                .iter()
+                .collect::<String>()
+                .parse()
+                .unwrap_or(1);
+            index = end + 1;
+        }
+        match c {
+            'S' => signed = true,
+            'V' => {}
+            'P' => digits += count,
+            '9' => {
+                digits += count;
+                size += count;
+            }
+            'N' => size += 2 * count,
+            _ => size += count,
+        }
+    }
+    (size, digits, signed)
+}
+
+/// The size of a binary item with `digits` digit positions, as with `binary-size: 1-2-4-8`.
+fn binary_size(digits: usize) -> usize {
+    match digits {
+        0..=2 => 1,
+        3..=4 => 2,
+        5..=9 => 4,
+        _ => 8,
+    }
+}
+
+impl DataDivision {
+    /// Read the data description entries between DATA DIVISION and PROCEDURE DIVISION.
+    /// Level 66 and 88 entries are skipped, and level 77 items are read like level 01 items.
+    pub fn parse(tokens: &[Token]) -> Self {
+        let start = tokens
+            .windows(2)
+            .position(|t| t[0].is_word("DATA") && t[1].is_word("DIVISION"))
+            .map_or(tokens.len(), |p| p + 2);
+        let end = tokens[start..]
+            .windows(2)
+            .position(|t| t[0].is_word("PROCEDURE") && t[1].is_word("DIVISION"))
+            .map_or(tokens.len(), |p| start + p);
+
+        let mut items: Vec<DataItem> = Vec::new();
+        let mut stack: Vec<usize> = Vec::new();
+        for entry in tokens[start..end].split(|t| t.kind == TokenKind::Period) {
+            let level: u32 = match entry.first() {
+                Some(t) if t.kind == TokenKind::Word => unwrap_ok_or!(t.text.parse(), _, continue),
+                _ => continue,
+            };
+            if !(1..=49).contains(&level) && level != 77 {
+                continue;
+            }
+            let level = if level == 77 { 1 } else { level };
+
+            let mut index = 1;
+            let name = match entry.get(1) {
+                Some(t)
+                    if t.kind == TokenKind::Word
+                        && !CLAUSE_WORDS.contains(&t.text.as_str())
+                        && Usage::from_word(&t.text).is_none() =>
+                {
+                    index += 1;
+                    t.text.clone()
+                }
+                _ => "FILLER".to_string(),
+            };
+            let mut item = DataItem {
+                level,
+                name,
+                parent: None,
+                picture: None,
+                usage: None,
+                occurs: 1,
+                redefines: false,
+                sign_separate: false,
+                file: entry[0].file.clone(),
+                line: entry[0].line,
+            };
+            while index < entry.len() {
+                let token = &entry[index];
+                index += 1;
+                if token.kind != TokenKind::Word {
+                    continue;
+                }
+                match token.text.as_str() {
+                    "PIC" | "PICTURE" => {
+                        if entry.get(index).is_some_and(|t| t.is_word("IS")) {
+                            index += 1;
+                        }
+                        let (picture, next) = read_picture(entry, index);
+                        item.picture = Some(picture);
+                        index = next;
+                    }
+                    "OCCURS" => {
+                        let mut occurs = entry.get(index).and_then(|t| t.text.parse().ok());
+                        if entry.get(index + 1).is_some_and(|t| t.is_word("TO")) {
+                            occurs = entry.get(index + 2).and_then(|t| t.text.parse().ok());
+                        }
+                        item.occurs = occurs.unwrap_or(1);
+                    }
+                    "REDEFINES" => {
+                        item.redefines = true;
+                        index += 1;
+                    }
+                    "SEPARATE" => item.sign_separate = true,
+                    word => {
+                        if let Some(usage) = Usage::from_word(word) {
+                            item.usage = Some(usage);
+                        }
+                    }
+                }
+            }
+
+            while stack
+                .last()
+                .is_some_and(|parent| items[*parent].level >= level)
+            {
+                stack.pop();
+            }
+            item.parent = stack.last().copied();
+            stack.push(items.len());
+            items.push(item);
+        }
+        Self { items }
+    }
+
+    pub fn is_group(&self, index: usize) -> bool {
+        self.items.iter().any(|item| item.parent == Some(index))
+    }
+
+    /// The usage of an item, which is inherited from its groups when it has none.
+    pub fn usage(&self, index: usize) -> Usage {
+        let mut current = Some(index);
+        while let Some(i) = current {
+            if let Some(usage) = self.items[i].usage {
+                return usage;
+            }
+            current = self.items[i].parent;
+        }
+        Usage::Display
+    }
+
+    /// The number of bytes one occurrence of an item takes.
+    pub fn size(&self, index: usize) -> usize {
+        if self.is_group(index) {
+            return self
+                .items
+                .iter()
+                .enumerate()
+                .filter(|(_, item)| item.parent == Some(index) && !item.redefines)
+                .map(|(i, item)| self.size(i) * item.occurs)
+                .sum();
+        }
+        let item = &self.items[index];
+        let (size, digits, signed) = picture_positions(item.picture.as_deref().unwrap_or(""));
+        match self.usage(index) {
+            Usage::Display | Usage::National => size + usize::from(signed && item.sign_separate),
+            Usage::Binary | Usage::NativeBinary(None) => binary_size(digits),
+            Usage::NativeBinary(Some(size)) | Usage::Float(size) => size,
+            Usage::PackedDecimal => digits / 2 + 1,
+            Usage::Pointer => 8,
+        }
+    }
+
+    /// Find the item that `reference` refers to, e.g. `A OF B(1)` or `A(3:4)`.
+    pub fn resolve(&self, reference: &str) -> Result<Reference, String> {
+        let mut words = String::new();
+        let mut subscripted = false;
+        let mut length = None;
+        let mut depth = 0;
+        let mut subscript = String::new();
+        for c in reference.chars() {
+            match c {
+                '(' => {
+                    depth += 1;
+                    subscript.clear();
+                }
+                ')' => {
+                    depth -= 1;
+                    match subscript.split_once(':') {
+                        Some((_, l)) => length = Some(l.trim().parse::<usize>().ok()),
+                        None => subscripted = true,
+                    }
+                }
+                _ if depth > 0 => subscript.push(c),
+                _ => words.push(c),
+            }
+        }
+        let words: Vec<&str> = words.split_whitespace().collect();
+        let name = unwrap_some_or!(words.first(), return Err("empty reference".to_string()));
+        let qualifiers: Vec<&str> = words[1..]
+            .chunks(2)
+            .filter_map(|pair| pair.get(1).copied())
+            .collect();
+
+        let candidates: Vec<usize> = (0..self.items.len())
+            .filter(|i| self.items[*i].name == *name)
+            .filter(|i| {
+                let mut ancestor = self.items[*i].parent;
+                qualifiers.iter().all(|qualifier| {
+                    while let Some(a) = ancestor {
+                        ancestor = self.items[a].parent;
+                        if self.items[a].name == *qualifier {
+                            return true;
+                        }
+                    }
+                    false
+                })
+            })
+            .collect();
+        let index = match candidates.as_slice() {
+            [] => return Err(format!("{} is not defined", reference)),
+            [index] => *index,
+            _ => return Err(format!("{} is ambiguous", reference)),
+        };
+        let size = match length {
+            Some(length) => length,
+            None if subscripted => Some(self.size(index)),
+            None => Some(self.size(index) * self.items[index].occurs),
+        };
+        Ok(Reference { index, size })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cobol_source::{source_tokens, SourceFormat};
+
+    fn data_division(source: &str) -> DataDivision {
+        DataDivision::parse(&source_tokens(source, "test.cbl", SourceFormat::Free))
+    }
+
+    fn size_of(data: &DataDivision, name: &str) -> usize {
+        let index = data.items.iter().position(|i| i.name == name).unwrap();
+        data.size(index)
+    }
+
+    #[test]
+    fn picture_positions_counts_bytes_and_digits() {
+        assert_eq!(picture_positions("X(10)"), (10, 0, false));
+        assert_eq!(picture_positions("S9(5)V9(2)"), (7, 7, true));
+        assert_eq!(picture_positions("S999"), (3, 3, true));
+        assert_eq!(picture_positions("N(3)"), (6, 0, false));
+        assert_eq!(picture_positions("9(3)PP"), (3, 5, false));
+        assert_eq!(picture_positions("ZZ9.99"), (6, 3, false));
+        assert_eq!(picture_positions(""), (0, 0, false));
+    }
+
+    #[test]
+    fn size_follows_usage() {
+        let data = data_division(
+            "DATA DIVISION.
+             WORKING-STORAGE SECTION.
+             01 D PIC S9(5).
+             01 D-SEP PIC S9(5) SIGN LEADING SEPARATE.
+             01 B2 PIC S9(4) USAGE BINARY.
+             01 B4 PIC S9(9) COMP.
+             01 B8 PIC S9(10) COMP-4.
+             01 N5 PIC 9(4) COMP-5.
+             01 L BINARY-LONG.
+             01 P PIC S9(7)V99 COMP-3.
+             01 F COMP-2.
+             01 PTR USAGE POINTER.
+             PROCEDURE DIVISION.",
+        );
+        assert_eq!(size_of(&data, "D"), 5);
+        assert_eq!(size_of(&data, "D-SEP"), 6);
+        assert_eq!(size_of(&data, "B2"), 2);
+        assert_eq!(size_of(&data, "B4"), 4);
+        assert_eq!(size_of(&data, "B8"), 8);
+        assert_eq!(size_of(&data, "N5"), 2);
+        assert_eq!(size_of(&data, "L"), 4);
+        assert_eq!(size_of(&data, "P"), 5);
+        assert_eq!(size_of(&data, "F"), 8);
+        assert_eq!(size_of(&data, "PTR"), 8);
+    }
+
+    #[test]
+    fn size_of_group_sums_children() {
+        let data = data_division(
+            "DATA DIVISION.
+             WORKING-STORAGE SECTION.
+             01 REC.
+                05 NAME PIC X(10).
+                05 CODES PIC 9(2) OCCURS 3.
+                05 ALT REDEFINES CODES PIC X(6).
+                05 NUMS USAGE BINARY.
+                   10 N1 PIC S9(4).
+                   10 N2 PIC S9(9).
+             77 FLAG PIC X.
+             PROCEDURE DIVISION.",
+        );
+        assert_eq!(size_of(&data, "NUMS"), 6);
+        assert_eq!(size_of(&data, "REC"), 22);
+        assert_eq!(size_of(&data, "FLAG"), 1);
+        assert_eq!(
+            data.usage(data.items.iter().position(|i| i.name == "N1").unwrap()),
+            Usage::Binary
+        );
+    }
+
+    #[test]
+    fn resolve_qualified_and_subscripted_references() {
+        let data = data_division(
+            "DATA DIVISION.
+             WORKING-STORAGE SECTION.
+             01 A.
+                05 X PIC X(4) OCCURS 5.
+             01 B.
+                05 X PIC X(8).
+             PROCEDURE DIVISION.",
+        );
+        assert_eq!(data.resolve("X OF B").unwrap().size, Some(8));
+        assert_eq!(data.resolve("X OF A").unwrap().size, Some(20));
+        assert_eq!(data.resolve("X OF A(2)").unwrap().size, Some(4));
+        assert_eq!(data.resolve("X OF B(1:3)").unwrap().size, Some(3));
+        assert!(data.resolve("X").unwrap_err().contains("ambiguous"));
+        assert!(data.resolve("Y").unwrap_err().contains("not defined"));
+    }
+}
@@ -0,0 +1,20 @@
+use crate::cparam::CParameter;
+
+/// An opensource COBOL 4J program called from C. `parameters` describe its
+/// LINKAGE items with the same type model as the parameters of C functions.
+#[derive(Clone, Debug)]
+pub struct CobolProgram {
+    pub program_name: String,
+    pub func_name: String,
+    pub parameters: Vec<CParameter>,
+}
+
+impl CobolProgram {
+    pub fn new() -> Self {
+        Self {
+            program_name: String::new(),
+            func_name: String::new(),
+            parameters: Vec::new(),
+        }
+    }
+}
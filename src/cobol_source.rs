@@ -0,0 +1,612 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SourceFormat {
+    Fixed,
+    Free,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TokenKind {
+    Word,
+    Literal,
+    PseudoText,
+    Period,
+    LeftParen,
+    RightParen,
+}
+
+/// A token of a COBOL source. Words are upper-cased, literals keep their
+/// quotes and case, and pseudo-text keeps the text between the `==` delimiters.
+#[derive(Clone, Debug)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub text: String,
+    pub file: String,
+    pub line: usize,
+}
+
+impl Token {
+    pub fn is_word(&self, word: &str) -> bool {
+        self.kind == TokenKind::Word && self.text == word
+    }
+
+    /// The value of a literal without its quotes and prefix.
+    pub fn literal_value(&self) -> String {
+        let text = self
+            .text
+            .trim_start_matches(|c: char| c.is_ascii_alphabetic());
+        let quote = text.chars().next().unwrap_or('"');
+        let inner = &text[1..text.len().saturating_sub(1).max(1)];
+        inner.replace(&format!("{}{}", quote, quote), &quote.to_string())
+    }
+}
+
+/// How deeply copybooks may include other copybooks, which also stops COPY cycles.
+const MAX_COPY_DEPTH: usize = 32;
+
+/// Read a COBOL source file as tokens, with its COPY statements replaced by the copybooks.
+/// Copybooks are looked up in the directory of the including file and then in `copy_paths`.
+pub fn read_tokens(
+    path: &str,
+    format: SourceFormat,
+    copy_paths: &[String],
+) -> Result<Vec<Token>, String> {
+    read_tokens_with_depth(path, format, copy_paths, 0)
+}
+
+fn read_tokens_with_depth(
+    path: &str,
+    format: SourceFormat,
+    copy_paths: &[String],
+    depth: usize,
+) -> Result<Vec<Token>, String> {
+    let source = fs::read_to_string(path).map_err(|_| format!("Unable to read {}", path))?;
+    let tokens = source_tokens(&source, path, format);
+    expand_copy_statements(tokens, path, format, copy_paths, depth)
+}
+
+/// The tokens of the source text of `path`, with its COPY statements left as they are.
+pub fn source_tokens(source: &str, path: &str, format: SourceFormat) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    for (line, text) in logical_lines(source, format) {
+        tokenize(&text, path, line, &mut tokens);
+    }
+    tokens
+}
+
+/// The program text of each line with comments and sequence areas removed.
+/// Continuation lines of fixed format are joined to the line they continue.
+fn logical_lines(source: &str, initial_format: SourceFormat) -> Vec<(usize, String)> {
+    let mut format = initial_format;
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    for (index, raw_line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let directive = raw_line.trim().to_uppercase();
+        if directive.starts_with(">>SOURCE") || directive.contains("$SET SOURCEFORMAT") {
+            if directive.contains("FREE") {
+                format = SourceFormat::Free;
+            } else if directive.contains("FIXED") {
+                format = SourceFormat::Fixed;
+            }
+            continue;
+        }
+        if directive.starts_with(">>") {
+            continue;
+        }
+        match format {
+            SourceFormat::Free => {
+                lines.push((line_number, strip_inline_comment(raw_line).to_string()));
+            }
+            SourceFormat::Fixed => {
+                let chars: Vec<char> = raw_line.chars().collect();
+                if chars.len() < 7 {
+                    continue;
+                }
+                let indicator = chars[6];
+                let area: String = chars[7..chars.len().min(72)].iter().collect();
+                match indicator {
+                    '*' | '/' | 'D' | 'd' => continue,
+                    '-' => {
+                        if let Some((_, previous)) = lines.last_mut() {
+                            let continued = area.trim_start();
+                            if unclosed_quote(previous).is_some() {
+                                // The continued literal resumes after the quote of this line.
+                                *previous += &continued.chars().skip(1).collect::<String>();
+                            } else {
+                                *previous = previous.trim_end().to_string();
+                                *previous += continued;
+                            }
+                            *previous = strip_inline_comment(previous).to_string();
+                        }
+                    }
+                    _ => {
+                        // A literal that is continued runs up to column 72.
+                        let text = if unclosed_quote(&area).is_some() {
+                            format!("{:<65}", area)
+                        } else {
+                            strip_inline_comment(&area).to_string()
+                        };
+                        lines.push((line_number, text));
+                    }
+                }
+            }
+        }
+    }
+    lines
+}
+
+fn unclosed_quote(text: &str) -> Option<char> {
+    let mut quote: Option<char> = None;
+    for c in text.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None => {}
+        }
+    }
+    quote
+}
+
+fn strip_inline_comment(text: &str) -> &str {
+    let mut quote: Option<char> = None;
+    let bytes = text.as_bytes();
+    for (index, c) in text.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '*' && bytes.get(index + 1) == Some(&b'>') => return &text[..index],
+            None => {}
+        }
+    }
+    text
+}
+
+fn is_separator(c: char) -> bool {
+    c.is_whitespace() || c == ',' || c == ';'
+}
+
+fn tokenize(text: &str, file: &str, line: usize, tokens: &mut Vec<Token>) {
+    let chars: Vec<char> = text.chars().collect();
+    let mut index = 0;
+    let mut push = |kind: TokenKind, text: String| {
+        tokens.push(Token {
+            kind,
+            text,
+            file: file.to_string(),
+            line,
+        })
+    };
+    while index < chars.len() {
+        let c = chars[index];
+        if is_separator(c) {
+            index += 1;
+        } else if c == '(' {
+            push(TokenKind::LeftParen, "(".to_string());
+            index += 1;
+        } else if c == ')' {
+            push(TokenKind::RightParen, ")".to_string());
+            index += 1;
+        } else if c == '.' && chars.get(index + 1).is_none_or(|n| n.is_whitespace()) {
+            push(TokenKind::Period, ".".to_string());
+            index += 1;
+        } else if c == '=' && chars.get(index + 1) == Some(&'=') {
+            let start = index + 2;
+            let mut end = start;
+            while end < chars.len() && !(chars[end] == '=' && chars.get(end + 1) == Some(&'=')) {
+                end += 1;
+            }
+            push(
+                TokenKind::PseudoText,
+                chars[start..end]
+                    .iter()
+                    .collect::<String>()
+                    .trim()
+                    .to_string(),
+            );
+            index = (end + 2).min(chars.len());
+        } else {
+            // A word, or a literal with an optional X, N, Z or NX prefix.
+            let start = index;
+            while index < chars.len() && chars[index].is_ascii_alphabetic() && index - start < 2 {
+                index += 1;
+            }
+            if index < chars.len() && (chars[index] == '"' || chars[index] == '\'') {
+                let quote = chars[index];
+                index += 1;
+                loop {
+                    if index >= chars.len() {
+                        break;
+                    }
+                    if chars[index] == quote {
+                        if chars.get(index + 1) == Some(&quote) {
+                            index += 2;
+                            continue;
+                        }
+                        index += 1;
+                        break;
+                    }
+                    index += 1;
+                }
+                push(
+                    TokenKind::Literal,
+                    chars[start..index].iter().collect::<String>(),
+                );
+                continue;
+            }
+            index = start;
+            while index < chars.len()
+                && !is_separator(chars[index])
+                && chars[index] != '('
+                && chars[index] != ')'
+                && chars[index] != '"'
+                && chars[index] != '\''
+                && !(chars[index] == '.' && chars.get(index + 1).is_none_or(|n| n.is_whitespace()))
+            {
+                index += 1;
+            }
+            push(
+                TokenKind::Word,
+                chars[start..index]
+                    .iter()
+                    .collect::<String>()
+                    .to_uppercase(),
+            );
+        }
+    }
+}
+
+/// A REPLACING operand of a COPY statement.
+struct Replacement {
+    from: Vec<Token>,
+    to: Vec<Token>,
+    leading: bool,
+    trailing: bool,
+}
+
+fn operand_tokens(token: &Token) -> Vec<Token> {
+    if token.kind == TokenKind::PseudoText {
+        let mut tokens = Vec::new();
+        tokenize(&token.text, &token.file, token.line, &mut tokens);
+        tokens
+    } else {
+        vec![token.clone()]
+    }
+}
+
+fn find_copybook(name: &str, including_file: &str, copy_paths: &[String]) -> Option<PathBuf> {
+    let mut directories = vec![Path::new(including_file)
+        .parent()
+        .map_or(PathBuf::from("."), |p| p.to_path_buf())];
+    directories.extend(copy_paths.iter().map(PathBuf::from));
+    for directory in directories.iter() {
+        for extension in ["", ".cpy", ".CPY", ".cbl", ".CBL", ".cob", ".COB"] {
+            let candidate = directory.join(format!("{}{}", name, extension));
+            if candidate.is_file() {
+                return Some(candidate);
+            }
+        }
+    }
+    None
+}
+
+fn expand_copy_statements(
+    tokens: Vec<Token>,
+    path: &str,
+    format: SourceFormat,
+    copy_paths: &[String],
+    depth: usize,
+) -> Result<Vec<Token>, String> {
+    let mut expanded = Vec::new();
+    let mut index = 0;
+    while index < tokens.len() {
+        if !tokens[index].is_word("COPY") {
+            expanded.push(tokens[index].clone());
+            index += 1;
+            continue;
+        }
+        let copy = &tokens[index];
+        let name_token = tokens
+            .get(index + 1)
+            .ok_or_else(|| format!("{}:{}: COPY without a name", copy.file, copy.line))?;
+        let name = match name_token.kind {
+            TokenKind::Literal => name_token.literal_value(),
+            _ => name_token.text.clone(),
+        };
+        index += 2;
+        let mut replacements = Vec::new();
+        while index < tokens.len() && tokens[index].kind != TokenKind::Period {
+            let token = &tokens[index];
+            if token.is_word("REPLACING") || token.is_word("ALSO") {
+                index += 1;
+                continue;
+            }
+            if token.is_word("OF") || token.is_word("IN") {
+                index += 2;
+                continue;
+            }
+            if token.is_word("SUPPRESS") || token.is_word("PRINTING") {
+                index += 1;
+                continue;
+            }
+            let leading = token.is_word("LEADING");
+            let trailing = token.is_word("TRAILING");
+            if leading || trailing {
+                index += 1;
+            }
+            let from = tokens.get(index);
+            let by = tokens.get(index + 1);
+            let to = tokens.get(index + 2);
+            match (from, by, to) {
+                (Some(from), Some(by), Some(to)) if by.is_word("BY") => {
+                    replacements.push(Replacement {
+                        from: operand_tokens(from),
+                        to: operand_tokens(to),
+                        leading,
+                        trailing,
+                    });
+                    index += 3;
+                }
+                _ => {
+                    return Err(format!(
+                        "{}:{}: unsupported COPY statement",
+                        copy.file, copy.line
+                    ))
+                }
+            }
+        }
+        index += 1;
+
+        if depth >= MAX_COPY_DEPTH {
+            return Err(format!(
+                "{}:{}: copybooks are nested too deeply",
+                copy.file, copy.line
+            ));
+        }
+        let copybook = find_copybook(&name, path, copy_paths)
+            .ok_or_else(|| format!("{}:{}: copybook {} not found", copy.file, copy.line, name))?;
+        let copybook_tokens =
+            read_tokens_with_depth(&copybook.to_string_lossy(), format, copy_paths, depth + 1)?;
+        expanded.extend(apply_replacements(copybook_tokens, &replacements));
+    }
+    Ok(expanded)
+}
+
+fn apply_replacements(tokens: Vec<Token>, replacements: &[Replacement]) -> Vec<Token> {
+    if replacements.is_empty() {
+        return tokens;
+    }
+    let mut replaced = Vec::new();
+    let mut index = 0;
+    'tokens: while index < tokens.len() {
+        for replacement in replacements.iter() {
+            if replacement.leading || replacement.trailing {
+                let (Some(from), token) = (replacement.from.first(), &tokens[index]) else {
+                    continue;
+                };
+                let to = replacement.to.first().map_or("", |t| t.text.as_str());
+                if token.kind == TokenKind::Word {
+                    let text = if replacement.leading {
+                        token
+                            .text
+                            .strip_prefix(from.text.as_str())
+                            .map(|rest| format!("{}{}", to, rest))
+                    } else {
+                        token
+                            .text
+                            .strip_suffix(from.text.as_str())
+                            .map(|rest| format!("{}{}", rest, to))
+                    };
+                    if let Some(text) = text {
+                        let mut token = token.clone();
+                        token.text = text;
+                        replaced.push(token);
+                        index += 1;
+                        continue 'tokens;
+                    }
+                }
+                continue;
+            }
+            // A `:TAG:` in pseudo-text is also replaced where it is part of a word.
+            if let ([from], token) = (replacement.from.as_slice(), &tokens[index]) {
+                if from.kind == TokenKind::Word
+                    && from.text.len() > 2
+                    && from.text.starts_with(':')
+                    && from.text.ends_with(':')
+                    && token.kind == TokenKind::Word
+                    && token.text != from.text
+                    && token.text.contains(from.text.as_str())
+                {
+                    let to: Vec<&str> = replacement.to.iter().map(|t| t.text.as_str()).collect();
+                    let mut token = token.clone();
+                    token.text = token.text.replace(from.text.as_str(), &to.join(" "));
+                    replaced.push(token);
+                    index += 1;
+                    continue 'tokens;
+                }
+            }
+            let length = replacement.from.len();
+            if length > 0
+                && index + length <= tokens.len()
+                && tokens[index..index + length]
+                    .iter()
+                    .zip(replacement.from.iter())
+                    .all(|(a, b)| a.kind == b.kind && a.text == b.text)
+            {
+                for to in replacement.to.iter() {
+                    let mut token = to.clone();
+                    token.file = tokens[index].file.clone();
+                    token.line = tokens[index].line;
+                    replaced.push(token);
+                }
+                index += length;
+                continue 'tokens;
+            }
+        }
+        replaced.push(tokens[index].clone());
+        index += 1;
+    }
+    replaced
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A directory of its own for each test, as the tests run in parallel.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("cobj-c-glue-test-{}-{}", std::process::id(), name));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write(dir: &Path, name: &str, content: &str) -> String {
+        let path = dir.join(name);
+        fs::write(&path, content).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    fn texts(tokens: &[Token]) -> Vec<&str> {
+        tokens.iter().map(|t| t.text.as_str()).collect()
+    }
+
+    #[test]
+    fn read_tokens_fixed_format() {
+        let dir = test_dir("fixed");
+        let path = write(
+            &dir,
+            "prog.cbl",
+            concat!(
+                "000100 IDENTIFICATION DIVISION.                                         SEQ00001\n",
+                "000200* A comment line.\n",
+                "000300 PROCEDURE DIVISION.\n",
+                "000400     call \"sub\" using a-b *> an inline comment\n",
+                "000500     DISPLAY \"HELLO, \n",
+                "000600-    \"WORLD\".\n",
+            ),
+        );
+        let tokens = read_tokens(&path, SourceFormat::Fixed, &[]).unwrap();
+        // The continued literal runs up to column 72.
+        let literal = format!("\"HELLO,{}WORLD\"", " ".repeat(46));
+        assert_eq!(
+            texts(&tokens),
+            vec![
+                "IDENTIFICATION",
+                "DIVISION",
+                ".",
+                "PROCEDURE",
+                "DIVISION",
+                ".",
+                "CALL",
+                "\"sub\"",
+                "USING",
+                "A-B",
+                "DISPLAY",
+                &literal,
+                ".",
+            ]
+        );
+        assert_eq!(tokens[6].line, 4);
+        assert_eq!(tokens[7].kind, TokenKind::Literal);
+        assert_eq!(tokens[2].kind, TokenKind::Period);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn read_tokens_free_format() {
+        let dir = test_dir("free");
+        let path = write(
+            &dir,
+            "prog.cbl",
+            "PROCEDURE DIVISION.\n  CALL 'sub' USING BY VALUE x(1) *> comment\n  .\n",
+        );
+        let tokens = read_tokens(&path, SourceFormat::Free, &[]).unwrap();
+        assert_eq!(
+            texts(&tokens),
+            vec![
+                "PROCEDURE",
+                "DIVISION",
+                ".",
+                "CALL",
+                "'sub'",
+                "USING",
+                "BY",
+                "VALUE",
+                "X",
+                "(",
+                "1",
+                ")",
+                "."
+            ]
+        );
+        assert_eq!(tokens[4].literal_value(), "sub");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn read_tokens_switches_format_with_directive() {
+        let dir = test_dir("directive");
+        let path = write(
+            &dir,
+            "prog.cbl",
+            "      >>SOURCE FORMAT IS FREE\nDISPLAY 'A'.\n",
+        );
+        let tokens = read_tokens(&path, SourceFormat::Fixed, &[]).unwrap();
+        assert_eq!(texts(&tokens), vec!["DISPLAY", "'A'", "."]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn read_tokens_copy_replacing() {
+        let dir = test_dir("copy");
+        let copy_dir = dir.join("copy");
+        fs::create_dir_all(&copy_dir).unwrap();
+        write(
+            &copy_dir,
+            "REC.cpy",
+            "01 :P:-REC.\n   05 :P:-NAME PIC X(10).\n   05 OLD-CODE PIC 9.\n",
+        );
+        let path = write(
+            &dir,
+            "prog.cbl",
+            "DATA DIVISION.\nCOPY REC REPLACING ==:P:== BY ==CUST== LEADING ==OLD== BY ==NEW==.\n",
+        );
+        let copy_paths = vec![copy_dir.to_string_lossy().to_string()];
+        let tokens = read_tokens(&path, SourceFormat::Free, &copy_paths).unwrap();
+        assert_eq!(
+            texts(&tokens),
+            vec![
+                "DATA",
+                "DIVISION",
+                ".",
+                "01",
+                "CUST-REC",
+                ".",
+                "05",
+                "CUST-NAME",
+                "PIC",
+                "X",
+                "(",
+                "10",
+                ")",
+                ".",
+                "05",
+                "NEW-CODE",
+                "PIC",
+                "9",
+                "."
+            ]
+        );
+        assert!(tokens[4].file.ends_with("REC.cpy"));
+
+        let missing = write(&dir, "missing.cbl", "COPY NOPE.\n");
+        assert!(read_tokens(&missing, SourceFormat::Free, &[])
+            .unwrap_err()
+            .contains("copybook NOPE not found"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
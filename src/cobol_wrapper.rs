@@ -0,0 +1,954 @@
+use crate::cconst::{CConstant, ConstantValue};
+use crate::cenum::CEnum;
+use crate::cfunc::CFunction;
+use crate::cparam::CParameter;
+use crate::cstruct::{BitField, CField, CStruct};
+use crate::java_type::PossibleJavaType;
+
+/// The maximum length of a COBOL user-defined word.
+const MAX_COBOL_WORD_LENGTH: usize = 30;
+
+/// C types that the glue passes as native bytes but that COBOL sees as integers.
+static NATIVE_INTEGER_TYPES: &[&str] = &[
+    "long",
+    "long int",
+    "long long",
+    "long long int",
+    "size_t",
+    "ssize_t",
+    "intptr_t",
+    "uintptr_t",
+    "int8_t",
+    "int16_t",
+    "int32_t",
+    "int64_t",
+    "uint8_t",
+    "uint16_t",
+    "uint32_t",
+    "uint64_t",
+];
+
+/// An argument of a wrapper program. The caller passes a LINKAGE item described by
+/// `linkage`; when `working` is given, the glue is called with a WORKING-STORAGE item
+/// of that description, which is moved from and to the LINKAGE item as needed.
+struct WrapperArgument {
+    name: String,
+    linkage: String,
+    working: Option<String>,
+    copy_in: bool,
+    copy_back: bool,
+    /// The digits of a binary WORKING-STORAGE item whose LINKAGE item has more, so
+    /// that a larger value would be truncated when it is moved in.
+    working_digits: Option<u32>,
+    /// Whether the LINKAGE item is text, which C gets without its trailing spaces
+    /// and with a NUL after it, and which is padded with spaces again on the way back.
+    text: bool,
+}
+
+impl WrapperArgument {
+    fn linkage_name(&self) -> String {
+        cobol_word("LK", &self.name)
+    }
+
+    fn working_name(&self) -> String {
+        cobol_word("WS", &self.name)
+    }
+
+    /// The item passed to the glue.
+    fn glue_name(&self) -> String {
+        if self.working.is_some() {
+            self.working_name()
+        } else {
+            self.linkage_name()
+        }
+    }
+}
+
+/// A COBOL user-defined word for a C identifier, e.g. `FIRST-NAME` for `first_name`.
+fn cobol_name(name: &str) -> String {
+    let mut word = name
+        .to_uppercase()
+        .replace('_', "-")
+        .trim_matches('-')
+        .to_string();
+    word.truncate(MAX_COBOL_WORD_LENGTH);
+    word.trim_end_matches('-').to_string()
+}
+
+/// A COBOL data name for a C identifier, e.g. `LK-FIRST-NAME` for `first_name`.
+fn cobol_word(prefix: &str, name: &str) -> String {
+    let name = cobol_name(name);
+    cobol_name(&format!(
+        "{}-{}",
+        prefix,
+        if name.is_empty() { "ARG" } else { &name }
+    ))
+}
+
+/// The last column of area B in fixed format.
+const MAX_LINE_LENGTH: usize = 72;
+
+/// A MOVE statement, split before TO when it does not fit on one line.
+fn move_statement(from: &str, to: &str) -> String {
+    let line = format!("           MOVE {} TO {}.\n", from, to);
+    if line.len() - 1 <= MAX_LINE_LENGTH {
+        return line;
+    }
+    format!("           MOVE {}\n               TO {}.\n", from, to)
+}
+
+/// The number of decimal digits of a binary item of `size` bytes.
+fn digits_of_size(size: u32) -> u32 {
+    match size {
+        1 => 2,
+        2 => 4,
+        4 => 9,
+        _ => 18,
+    }
+}
+
+fn sign(unsigned: bool) -> &'static str {
+    if unsigned {
+        ""
+    } else {
+        "S"
+    }
+}
+
+/// A DISPLAY item in LINKAGE that is moved to a big-endian binary item for the glue.
+fn binary_argument(
+    name: &str,
+    digits: u32,
+    unsigned: bool,
+    copy_in: bool,
+    copy_back: bool,
+) -> WrapperArgument {
+    WrapperArgument {
+        name: name.to_string(),
+        linkage: format!("PIC {}9({})", sign(unsigned), digits),
+        working: Some(format!("PIC {}9({}) USAGE BINARY", sign(unsigned), digits)),
+        copy_in,
+        copy_back,
+        working_digits: None,
+        text: false,
+    }
+}
+
+/// A binary argument of `size` bytes whose LINKAGE item has one digit more than its
+/// binary item, e.g. PIC S9(10) for the 4 bytes of an `int`, so that any value C writes
+/// is copied back whole. A value moved in must fit the binary item, whose PIC S9(9)
+/// keeps 4 bytes, so the wrapper sets RETURN-CODE to -1 without calling C otherwise.
+fn widened_binary_argument(
+    name: &str,
+    size: u32,
+    unsigned: bool,
+    copy_back: bool,
+) -> WrapperArgument {
+    let digits = digits_of_size(size);
+    WrapperArgument {
+        linkage: format!("PIC {}9({})", sign(unsigned), digits + 1),
+        working_digits: Some(digits),
+        ..binary_argument(name, digits, unsigned, true, copy_back)
+    }
+}
+
+fn wrapper_argument(parameter: &CParameter) -> WrapperArgument {
+    let unsigned = parameter.type_name.starts_with("unsigned");
+    let name = &parameter.var_name;
+    match parameter.java_type {
+        PossibleJavaType::Short => {
+            widened_binary_argument(name, 2, unsigned, parameter.copies_back_value())
+        }
+        PossibleJavaType::Int => {
+            widened_binary_argument(name, 4, unsigned, parameter.copies_back_value())
+        }
+        PossibleJavaType::Handle => binary_argument(name, 9, false, true, parameter.release_handle),
+        PossibleJavaType::HandleOut => binary_argument(name, 9, false, false, true),
+        PossibleJavaType::Byte => WrapperArgument {
+            name: name.to_string(),
+            linkage: "PIC X".to_string(),
+            working: None,
+            copy_in: false,
+            copy_back: false,
+            working_digits: None,
+            text: false,
+        },
+        PossibleJavaType::CString => WrapperArgument {
+            name: name.to_string(),
+            linkage: format!("PIC X({})", parameter.string_length),
+            working: Some(format!("PIC X({})", parameter.string_length + 1)),
+            copy_in: true,
+            copy_back: parameter.copies_back(),
+            working_digits: None,
+            text: true,
+        },
+        PossibleJavaType::ByteArray | PossibleJavaType::Callback => {
+            let base_type = parameter
+                .type_name
+                .trim_start_matches("unsigned ")
+                .trim_start_matches("signed ");
+            let (linkage, working) = if parameter.pointer_depth > 1 || parameter.is_array() {
+                (format!("PIC X({})", parameter.type_size), None)
+            } else if NATIVE_INTEGER_TYPES.contains(&base_type) {
+                let unsigned = unsigned || base_type.starts_with('u') || base_type == "size_t";
+                let digits = digits_of_size(parameter.type_size);
+                (
+                    format!("PIC {}9({})", sign(unsigned), digits),
+                    Some(format!("PIC {}9({}) USAGE COMP-5", sign(unsigned), digits)),
+                )
+            } else if base_type == "float" {
+                (
+                    "PIC S9(9)V9(9)".to_string(),
+                    Some("USAGE COMP-1".to_string()),
+                )
+            } else if base_type == "double" {
+                (
+                    "PIC S9(9)V9(9)".to_string(),
+                    Some("USAGE COMP-2".to_string()),
+                )
+            } else {
+                (format!("PIC X({})", parameter.type_size), None)
+            };
+            WrapperArgument {
+                name: name.to_string(),
+                linkage,
+                working,
+                copy_in: true,
+                copy_back: parameter.copies_back(),
+                working_digits: None,
+                text: false,
+            }
+        }
+    }
+}
+
+/// The PROGRAM-ID of the wrapper of `c_function`.
+pub fn wrapper_program_name(c_function: &CFunction) -> String {
+    format!("{}_cobol", c_function.name)
+}
+
+/// Items of a USING phrase, one per line so that long lists stay within area B.
+fn using_lines(names: &[String], indent: &str) -> String {
+    let mut s = String::new();
+    for (i, name) in names.iter().enumerate() {
+        s += &format!("\n{}{}", indent, name);
+        if i + 1 == names.len() {
+            s += ".";
+        }
+    }
+    s
+}
+
+/// A fixed-format COBOL subprogram that takes DISPLAY numerics and PIC X items,
+/// converts them to the binary layout the generated glue reads, and calls the glue.
+pub fn get_cobol_wrapper_file_content(c_function: &CFunction) -> String {
+    let mut arguments: Vec<WrapperArgument> = c_function
+        .parameters
+        .iter()
+        .filter(|p| p.callback.is_none())
+        .map(wrapper_argument)
+        .collect();
+    if c_function.return_handle {
+        arguments.push(binary_argument("returned_handle", 9, false, false, true));
+    }
+
+    let mut s = "".to_string();
+    s += &format!(
+        "      * Calls the glue of the C function {}.\n",
+        c_function.name
+    );
+    s += "      * Generated by cobj-c-glue.\n";
+    s += "       IDENTIFICATION DIVISION.\n";
+    s += &format!(
+        "       PROGRAM-ID. \"{}\".\n",
+        wrapper_program_name(c_function)
+    );
+    s += "       DATA DIVISION.\n";
+    s += "       WORKING-STORAGE SECTION.\n";
+    for argument in arguments.iter() {
+        if let Some(working) = &argument.working {
+            s += &format!("       01 {} {}.\n", argument.working_name(), working);
+        }
+    }
+    s += "       LINKAGE SECTION.\n";
+    for argument in arguments.iter() {
+        s += &format!(
+            "       01 {} {}.\n",
+            argument.linkage_name(),
+            argument.linkage
+        );
+    }
+
+    let linkage_names: Vec<String> = arguments.iter().map(|a| a.linkage_name()).collect();
+    let glue_names: Vec<String> = arguments.iter().map(|a| a.glue_name()).collect();
+    if arguments.is_empty() {
+        s += "       PROCEDURE DIVISION.\n";
+    } else {
+        s += &format!(
+            "       PROCEDURE DIVISION USING{}\n",
+            using_lines(&linkage_names, "           ")
+        );
+    }
+    for argument in arguments.iter() {
+        if let Some(digits) = argument.working_digits {
+            s += &format!("           IF FUNCTION ABS({})\n", argument.linkage_name());
+            s += &format!("                   > {}\n", "9".repeat(digits as usize));
+            s += "               MOVE -1 TO RETURN-CODE\n";
+            s += "               GOBACK\n";
+            s += "           END-IF.\n";
+        }
+    }
+    for argument in arguments
+        .iter()
+        .filter(|a| a.working.is_some() && a.copy_in)
+    {
+        if argument.text {
+            // The item is filled with NULs first, so the text ends with one.
+            s += &move_statement("LOW-VALUE", &argument.working_name());
+            s += &format!(
+                "           STRING FUNCTION TRIM({} TRAILING)\n",
+                argument.linkage_name()
+            );
+            s += &format!(
+                "               DELIMITED BY SIZE INTO {}.\n",
+                argument.working_name()
+            );
+        } else {
+            s += &move_statement(&argument.linkage_name(), &argument.working_name());
+        }
+    }
+    if arguments.is_empty() {
+        s += &format!("           CALL \"{}\".\n", c_function.name);
+    } else {
+        s += &format!(
+            "           CALL \"{}\" USING{}\n",
+            c_function.name,
+            using_lines(&glue_names, "               ")
+        );
+    }
+    for argument in arguments
+        .iter()
+        .filter(|a| a.working.is_some() && a.copy_back)
+    {
+        if argument.text {
+            s += &format!("           UNSTRING {}\n", argument.working_name());
+            s += "               DELIMITED BY LOW-VALUE\n";
+            s += &format!("               INTO {}.\n", argument.linkage_name());
+        } else {
+            s += &move_statement(&argument.working_name(), &argument.linkage_name());
+        }
+    }
+    s += "           GOBACK.\n";
+    s
+}
+
+/// The copybook that `generate_cobol` writes the enum constants to.
+pub static ENUM_COPYBOOK_PATH: &str = "enums.cpy";
+
+/// A copybook with a level-78 constant for each enum constant of the C header,
+/// so that COBOL programs can compare return codes and pass enum arguments by name.
+/// Constants such as `OK` or `ERROR` are COBOL reserved words, so each name is prefixed
+/// with its enum, e.g. `STATUS-OK` for `enum status`, or with `prefix` if the enum is anonymous.
+pub fn get_cobol_enum_copybook_content(c_enums: &[CEnum], prefix: &str) -> String {
+    let mut s = "".to_string();
+    s += "      * The enum constants of the C header.\n";
+    s += "      * Generated by cobj-c-glue.\n";
+    for c_enum in c_enums.iter() {
+        if let Some(type_name) = c_enum.type_names.first() {
+            s += &format!("      * {}\n", type_name);
+        }
+        let enum_prefix = match c_enum.type_names.first() {
+            Some(type_name) => type_name.trim_start_matches("enum "),
+            None => prefix,
+        };
+        for constant in c_enum.constants.iter() {
+            s += &format!(
+                "       78 {} VALUE {}.\n",
+                cobol_name(&format!("{}_{}", enum_prefix, constant.name)),
+                constant.value
+            );
+        }
+    }
+    s
+}
+
+pub static CONSTANT_COPYBOOK_PATH: &str = "constants.cpy";
+
+/// The maximum number of digits in a COBOL numeric literal.
+const MAX_NUMERIC_LITERAL_DIGITS: usize = 31;
+
+/// The start of a continuation line of a literal: the hyphen in the indicator area
+/// and the quotation mark that resumes the literal.
+const LITERAL_CONTINUATION: &str = "      -    \"";
+
+/// A level-78 entry for `literal`, e.g. `"abc"` or `X"0A"`. A literal that does not fit
+/// goes on its own line and continues on the next ones, each filled up to column 72.
+fn constant_entry(name: &str, literal: &str) -> String {
+    let line = format!("       78 {} VALUE {}.\n", name, literal);
+    if line.len() - 1 <= MAX_LINE_LENGTH {
+        return line;
+    }
+    let continuation_length = MAX_LINE_LENGTH - LITERAL_CONTINUATION.len();
+    // Indent the VALUE clause so that the closing quotation mark and the period
+    // do not start a continuation line of their own.
+    let mut indent = 11;
+    let mut value = format!("{}VALUE {}.", " ".repeat(indent), literal);
+    while value.len() > MAX_LINE_LENGTH && (value.len() - MAX_LINE_LENGTH) % continuation_length < 3
+    {
+        indent += 1;
+        value = format!("{}VALUE {}.", " ".repeat(indent), literal);
+    }
+    let mut s = format!("       78 {}\n", name);
+    let first_length = value.len().min(MAX_LINE_LENGTH);
+    s += &value[..first_length];
+    s += "\n";
+    for chunk in value.as_bytes()[first_length..].chunks(continuation_length) {
+        s += LITERAL_CONTINUATION;
+        s += &String::from_utf8_lossy(chunk);
+        s += "\n";
+    }
+    s
+}
+
+/// A copybook with a level-78 constant for each constant macro of the C header.
+/// Strings with control characters become hexadecimal literals. Each name is prefixed
+/// with `prefix`, e.g. `C-PI` for `PI`, as macros may be COBOL reserved words too.
+pub fn get_cobol_constant_copybook_content(c_constants: &[CConstant], prefix: &str) -> String {
+    let mut s = "".to_string();
+    s += "      * The constant macros of the C header.\n";
+    s += "      * Generated by cobj-c-glue.\n";
+    for constant in c_constants.iter() {
+        let name = cobol_name(&format!("{}_{}", prefix, constant.name));
+        let literal = match &constant.value {
+            ConstantValue::Integer(value) => Some(value.to_string()),
+            ConstantValue::Real(value) => Some(value.to_string()).filter(|literal| {
+                value.is_finite()
+                    && literal.chars().filter(|c| c.is_ascii_digit()).count()
+                        <= MAX_NUMERIC_LITERAL_DIGITS
+            }),
+            ConstantValue::String(value) if value.is_empty() => None,
+            ConstantValue::String(value) if value.bytes().all(|c| (0x20..0x7f).contains(&c)) => {
+                Some(format!("\"{}\"", value.replace('"', "\"\"")))
+            }
+            ConstantValue::String(value) => Some(format!(
+                "X\"{}\"",
+                value
+                    .bytes()
+                    .map(|c| format!("{:02X}", c))
+                    .collect::<String>()
+            )),
+        };
+        match literal {
+            Some(literal) => s += &constant_entry(&name, &literal),
+            None => s += &format!("      * {} has no COBOL literal.\n", constant.name),
+        }
+    }
+    s
+}
+
+/// The copybook that `generate_cobol` writes the struct layouts to.
+pub static STRUCT_COPYBOOK_PATH: &str = "structs.cpy";
+
+/// The copybook with the items that the bitfield paragraphs read and write.
+pub static BITFIELD_COPYBOOK_PATH: &str = "bitfields.cpy";
+
+/// The copybook with a GET- and a SET- paragraph for each bitfield.
+pub static BITFIELD_ACCESSOR_COPYBOOK_PATH: &str = "bitfield-accessors.cpy";
+
+/// The level number of the fields of a record.
+const FIELD_LEVEL: u32 = 5;
+
+/// The words of `text` as an entry or a statement that starts at column `indent + 1`.
+/// Words that do not fit go on the next lines, indented by four more columns.
+fn wrap_words(indent: usize, text: &str) -> String {
+    let mut s = String::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if line.is_empty() {
+            line = format!("{}{}", " ".repeat(indent), word);
+        } else if line.len() + 1 + word.len() > MAX_LINE_LENGTH {
+            s += &line;
+            s += "\n";
+            line = format!("{}{}", " ".repeat(indent + 4), word);
+        } else {
+            line += " ";
+            line += word;
+        }
+    }
+    s + &line + "\n"
+}
+
+/// An entry of `level`, indented four columns per level below 01.
+fn data_entry(level: u32, text: &str) -> String {
+    wrap_words(
+        7 + 4 * (level / 5) as usize,
+        &format!("{:02} {}.", level, text),
+    )
+}
+
+/// Whether a C integer type is unsigned, or `None` if the type is not an integer.
+/// Characters are text to COBOL, so `char` types are not integers here.
+fn integer_signedness(type_name: &str) -> Option<bool> {
+    let words: Vec<&str> = type_name.split_whitespace().collect();
+    let unsigned = words.first() == Some(&"unsigned");
+    let base_type = type_name
+        .trim_start_matches("unsigned ")
+        .trim_start_matches("signed ");
+    if NATIVE_INTEGER_TYPES.contains(&base_type) {
+        return Some(unsigned || base_type.starts_with('u') || base_type == "size_t");
+    }
+    if matches!(type_name, "bool" | "_Bool") {
+        return Some(true);
+    }
+    if !words.is_empty()
+        && words
+            .iter()
+            .all(|w| matches!(*w, "signed" | "unsigned" | "short" | "int" | "long"))
+    {
+        return Some(unsigned);
+    }
+    None
+}
+
+/// The USAGE and PICTURE of an element of `c_field` that is `size` bytes long.
+fn element_description(c_field: &CField, size: u32) -> String {
+    if c_field.pointer_depth > 0 {
+        return "USAGE POINTER".to_string();
+    }
+    let unsigned = if c_field.is_enum {
+        Some(false)
+    } else {
+        integer_signedness(&c_field.type_name)
+    };
+    match (unsigned, c_field.type_name.as_str(), size) {
+        (Some(unsigned), _, 1 | 2 | 4 | 8) => format!(
+            "PIC {}9({}) USAGE COMP-5",
+            sign(unsigned),
+            digits_of_size(size)
+        ),
+        (_, "float", 4) => "USAGE COMP-1".to_string(),
+        (_, "double", 8) => "USAGE COMP-2".to_string(),
+        _ => format!("PIC X({})", size),
+    }
+}
+
+/// The struct or union that a field holds, defined in place or elsewhere in the header.
+fn nested_struct<'a>(c_field: &'a CField, c_structs: &'a [CStruct]) -> Option<&'a CStruct> {
+    if c_field.pointer_depth > 0 || c_field.bitfield.is_some() {
+        return None;
+    }
+    c_field.members.as_ref().or_else(|| {
+        c_structs
+            .iter()
+            .find(|s| s.type_names.contains(&c_field.type_name))
+    })
+}
+
+/// The data name of each field of `c_struct`, which is held by the item `group`, and the
+/// prefix of the names of its members. Fields are named after `prefix`, e.g. `ORDER-ITEMS`
+/// for the field `items` of `ORDER`. An anonymous member is named after its position among
+/// the anonymous members, e.g. `ORDER-ANONYMOUS-1`, and its members after `prefix`.
+fn member_names(prefix: &str, group: &str, c_struct: &CStruct) -> Vec<(String, String)> {
+    let anonymous_prefix = if group == prefix {
+        cobol_word(prefix, "anonymous")
+    } else {
+        group.to_string()
+    };
+    let mut num_of_anonymous = 0;
+    c_struct
+        .fields
+        .iter()
+        .map(|c_field| {
+            if c_field.is_anonymous() {
+                num_of_anonymous += 1;
+                (
+                    cobol_name(&format!("{}-{}", anonymous_prefix, num_of_anonymous)),
+                    prefix.to_string(),
+                )
+            } else {
+                let name = cobol_word(prefix, &c_field.name);
+                (name.clone(), name)
+            }
+        })
+        .collect()
+}
+
+/// The entries that describe `c_field` as `name` at `level`. An array is a group of
+/// OCCURS items, `NAME-DIM-k` for the outer dimensions and `NAME-ITEM` for the elements,
+/// except that the last dimension of a character array is the length of its text.
+/// A struct or union is a group of the entries of its fields, named after `member_prefix`.
+fn field_entries(
+    name: &str,
+    member_prefix: &str,
+    c_field: &CField,
+    level: u32,
+    redefines: Option<&str>,
+    c_structs: &[CStruct],
+) -> String {
+    let redefines = match redefines {
+        Some(base) => format!(" REDEFINES {}", base),
+        None => String::new(),
+    };
+    let mut dimensions: Vec<u32> = c_field
+        .array_dimensions
+        .iter()
+        .filter_map(|d| d.parse().ok())
+        .collect();
+    let num_of_elements: u32 = dimensions.iter().product();
+    if dimensions.len() != c_field.array_dimensions.len() || num_of_elements == 0 {
+        return data_entry(
+            level,
+            &format!("{}{} PIC X({})", name, redefines, c_field.size),
+        );
+    }
+    let mut element_size = c_field.size / num_of_elements;
+    let nested = nested_struct(c_field, c_structs);
+    if nested.is_none()
+        && c_field.pointer_depth == 0
+        && c_field.type_name.split_whitespace().any(|w| w == "char")
+    {
+        if let Some(length) = dimensions.pop() {
+            element_size *= length;
+        }
+    }
+
+    let mut s = String::new();
+    let mut level = level;
+    let mut item = format!("{}{}", name, redefines);
+    for (k, dimension) in dimensions.iter().enumerate() {
+        s += &data_entry(level, &item);
+        level += 5;
+        let suffix = if k + 1 == dimensions.len() {
+            "ITEM".to_string()
+        } else {
+            format!("DIM-{}", k + 1)
+        };
+        item = format!(
+            "{} OCCURS {}",
+            cobol_name(&format!("{}-{}", name, suffix)),
+            dimension
+        );
+    }
+    match nested {
+        Some(nested) => {
+            s += &data_entry(level, &item);
+            s += &record_entries(
+                member_prefix,
+                name,
+                nested,
+                level + 5,
+                c_structs,
+                !dimensions.is_empty(),
+            );
+        }
+        None => {
+            s += &data_entry(
+                level,
+                &format!("{} {}", item, element_description(c_field, element_size)),
+            )
+        }
+    }
+    s
+}
+
+fn filler_entry(level: u32, size: u32) -> String {
+    data_entry(level, &format!("FILLER PIC X({})", size))
+}
+
+/// The prefix of the names of the fields of a struct, e.g. `POINT` for `struct point`.
+fn record_prefix(c_struct: &CStruct) -> String {
+    cobol_name(
+        c_struct
+            .c_type_name()
+            .split_whitespace()
+            .last()
+            .unwrap_or(""),
+    )
+}
+
+/// The name of the 01 record of a struct, e.g. `POINT-RECORD`. Names of C types such
+/// as `line` or `order` are often reserved words in COBOL.
+fn record_name(c_struct: &CStruct) -> String {
+    cobol_word(&record_prefix(c_struct), "record")
+}
+
+/// The entries of the fields of a record at `level` in the order of their offsets. A field
+/// at the offset of the one before it, as in a union, redefines it, and gaps are FILLER.
+/// Bitfields are left in the FILLER, as COBOL has no items smaller than a byte; their
+/// paragraphs cannot address an element of an OCCURS item, so those have none.
+fn record_entries(
+    prefix: &str,
+    group: &str,
+    c_struct: &CStruct,
+    level: u32,
+    c_structs: &[CStruct],
+    in_occurs: bool,
+) -> String {
+    let names = member_names(prefix, group, c_struct);
+    let mut c_fields: Vec<(&CField, &(String, String))> =
+        c_struct.fields.iter().zip(names.iter()).collect();
+    c_fields.sort_by_key(|(f, _)| (f.offset, std::cmp::Reverse(f.size)));
+
+    let mut s = String::new();
+    let mut end = 0;
+    let mut base: Option<(&str, u32, u32)> = None;
+    for (c_field, (name, member_prefix)) in c_fields {
+        if c_field.bitfield.is_some() {
+            if in_occurs {
+                s += &format!("      * Bitfield {} has no paragraphs.\n", name);
+            } else {
+                s += &format!(
+                    "      * Bitfield {}, see {}.\n",
+                    name, BITFIELD_COPYBOOK_PATH
+                );
+            }
+            continue;
+        }
+        if c_field.size == 0 {
+            s += &format!("      * {} has no size and is not described.\n", name);
+            continue;
+        }
+        if c_field.offset >= end {
+            if c_field.offset > end {
+                s += &filler_entry(level, c_field.offset - end);
+            }
+            s += &field_entries(name, member_prefix, c_field, level, None, c_structs);
+            end = c_field.offset + c_field.size;
+            base = Some((name, c_field.offset, c_field.size));
+            continue;
+        }
+        match base {
+            Some((base_name, offset, size)) if offset == c_field.offset && c_field.size <= size => {
+                s += &field_entries(
+                    name,
+                    member_prefix,
+                    c_field,
+                    level,
+                    Some(base_name),
+                    c_structs,
+                );
+            }
+            _ => {
+                s += &format!(
+                    "      * {} overlaps another field and is not described.\n",
+                    name
+                )
+            }
+        }
+    }
+    if c_struct.size > end {
+        s += &filler_entry(level, c_struct.size - end);
+    }
+    s
+}
+
+/// A copybook with an 01 record for each struct and union of the C header, laid out as
+/// the C compiler lays them out, so that COBOL programs can pass them by reference.
+pub fn get_cobol_struct_copybook_content(c_structs: &[CStruct]) -> String {
+    let mut s = "".to_string();
+    s += "      * The structs and unions of the C header.\n";
+    s += "      * Generated by cobj-c-glue.\n";
+    for c_struct in c_structs.iter() {
+        let record = record_name(c_struct);
+        let prefix = record_prefix(c_struct);
+        s += &format!("      * {}\n", c_struct.type_names.join(", "));
+        if c_struct.size == 0 {
+            s += &format!("      * {} has no size and is not described.\n", record);
+            continue;
+        }
+        s += &data_entry(1, &record);
+        s += &record_entries(&prefix, &prefix, c_struct, FIELD_LEVEL, c_structs, false);
+    }
+    s
+}
+
+/// The item that a bitfield paragraph moves a storage unit of `size` bytes to.
+fn bitfield_unit_bytes_name(size: u32) -> String {
+    format!("OC4J-GLUE-BYTES-{}", size)
+}
+
+/// The unit of `bitfield_unit_bytes_name` as an unsigned integer.
+fn bitfield_unit_name(size: u32) -> String {
+    format!("OC4J-GLUE-BITS-{}", size)
+}
+
+/// A bitfield that has paragraphs, and the item they read it to.
+struct BitFieldItem<'a> {
+    name: String,
+    record: String,
+    /// The offset of the storage unit in the record.
+    offset: u32,
+    size: u32,
+    bitfield: &'a BitField,
+}
+
+/// Add the bitfields of `c_struct`, which is at `offset` in `record`, and of the structs
+/// it holds except in arrays, whose elements the paragraphs cannot address.
+fn collect_bitfields<'a>(
+    record: &str,
+    prefix: &str,
+    group: &str,
+    c_struct: &'a CStruct,
+    offset: u32,
+    c_structs: &'a [CStruct],
+    items: &mut Vec<BitFieldItem<'a>>,
+) {
+    for (c_field, (name, member_prefix)) in c_struct
+        .fields
+        .iter()
+        .zip(member_names(prefix, group, c_struct))
+    {
+        if let Some(bitfield) = &c_field.bitfield {
+            items.push(BitFieldItem {
+                name,
+                record: record.to_string(),
+                offset: offset + c_field.offset,
+                size: c_field.size,
+                bitfield,
+            });
+        } else if let Some(nested) = nested_struct(c_field, c_structs) {
+            if !c_field.is_array() {
+                collect_bitfields(
+                    record,
+                    &member_prefix,
+                    &name,
+                    nested,
+                    offset + c_field.offset,
+                    c_structs,
+                    items,
+                );
+            }
+        }
+    }
+}
+
+/// The bitfields of the records with the names of their items, in the order of the header.
+fn bitfields(c_structs: &[CStruct]) -> Vec<BitFieldItem<'_>> {
+    let mut items = Vec::new();
+    for c_struct in c_structs.iter() {
+        let prefix = record_prefix(c_struct);
+        collect_bitfields(
+            &record_name(c_struct),
+            &prefix,
+            &prefix,
+            c_struct,
+            0,
+            c_structs,
+            &mut items,
+        );
+    }
+    items
+}
+
+/// A copybook with an item for the value of each bitfield, and the storage units that
+/// the paragraphs of the accessor copybook use to read and write the records.
+pub fn get_cobol_bitfield_copybook_content(c_structs: &[CStruct]) -> String {
+    let bitfields = bitfields(c_structs);
+    let mut unit_sizes: Vec<u32> = bitfields.iter().map(|b| b.size).collect();
+    unit_sizes.sort();
+    unit_sizes.dedup();
+
+    let mut s = "".to_string();
+    s += "      * The values of the bitfields of the C header. PERFORM GET-name\n";
+    s += "      * to read a bitfield of its record and SET-name to write it.\n";
+    s += "      * Generated by cobj-c-glue.\n";
+    for size in unit_sizes {
+        s += &data_entry(
+            1,
+            &format!("{} PIC X({})", bitfield_unit_bytes_name(size), size),
+        );
+        s += &data_entry(
+            1,
+            &format!(
+                "{} REDEFINES {} PIC 9({}) USAGE COMP-5",
+                bitfield_unit_name(size),
+                bitfield_unit_bytes_name(size),
+                digits_of_size(size)
+            ),
+        );
+    }
+    let mut records = Vec::new();
+    for BitFieldItem {
+        name,
+        record,
+        bitfield,
+        ..
+    } in bitfields.iter()
+    {
+        let max_value = if bitfield.signed {
+            1u128 << (bitfield.bit_width - 1)
+        } else {
+            (1u128 << bitfield.bit_width) - 1
+        };
+        if !records.contains(&record) {
+            records.push(record);
+            s += &format!("      * {}\n", record);
+        }
+        s += &data_entry(
+            1,
+            &format!(
+                "{} PIC {}9({})",
+                name,
+                sign(!bitfield.signed),
+                max_value.to_string().len()
+            ),
+        );
+    }
+    s
+}
+
+/// A copybook with the paragraphs that read and write the bitfields of the records.
+/// The storage unit of a bitfield is moved out of the record, and the bits of the
+/// field are computed from its value; signed fields are sign-extended.
+pub fn get_cobol_bitfield_accessor_copybook_content(c_structs: &[CStruct]) -> String {
+    let mut s = "".to_string();
+    s += "      * The paragraphs that read and write the bitfields of the C header.\n";
+    s += "      * COPY them after the last statement of the PROCEDURE DIVISION.\n";
+    s += "      * Generated by cobj-c-glue.\n";
+    for BitFieldItem {
+        name,
+        record,
+        offset,
+        size,
+        bitfield,
+        ..
+    } in bitfields(c_structs).iter()
+    {
+        let bytes = bitfield_unit_bytes_name(*size);
+        let unit = bitfield_unit_name(*size);
+        let record_bytes = format!("{}({}:{})", record, offset + 1, size);
+        let divisor = 1u128 << bitfield.bit_offset;
+        let modulus = 1u128 << bitfield.bit_width;
+        let bits = format!(
+            "FUNCTION MOD(FUNCTION INTEGER-PART({} / {}), {})",
+            unit, divisor, modulus
+        );
+
+        s += &format!("       {}.\n", cobol_word("GET", name));
+        s += &wrap_words(11, &format!("MOVE {} TO {}.", record_bytes, bytes));
+        if bitfield.signed {
+            let half = modulus / 2;
+            s += &wrap_words(
+                11,
+                &format!(
+                    "COMPUTE {} = FUNCTION MOD(FUNCTION INTEGER-PART({} / {}) + {}, {}) - {}.",
+                    name, unit, divisor, half, modulus, half
+                ),
+            );
+        } else {
+            s += &wrap_words(11, &format!("COMPUTE {} = {}.", name, bits));
+        }
+
+        s += &format!("       {}.\n", cobol_word("SET", name));
+        s += &wrap_words(11, &format!("MOVE {} TO {}.", record_bytes, bytes));
+        s += &wrap_words(
+            11,
+            &format!(
+                "COMPUTE {} = {} - {} * {} + FUNCTION MOD({}, {}) * {}.",
+                unit, unit, bits, divisor, name, modulus, divisor
+            ),
+        );
+        s += &wrap_words(11, &format!("MOVE {} TO {}.", bytes, record_bytes));
+    }
+    s
+}
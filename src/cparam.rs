@@ -1,5 +1,45 @@
+use crate::ccallback::CCallback;
 use crate::java_type::PossibleJavaType;
 use tree_sitter::Node;
+use unwrap_or::unwrap_some_or;
+
+/// Type qualifiers that may be written before or after the type specifier.
+/// `restrict` only says how the pointer is used, so it is dropped.
+pub static TYPE_QUALIFIERS: &[&str] = &[
+    "const",
+    "volatile",
+    "restrict",
+    "__restrict",
+    "__restrict__",
+];
+
+/// The most characters a `char *` parameter holds when the schema gives no `string_length`.
+pub static DEFAULT_STRING_LENGTH: u32 = 256;
+
+/// Whether the glue copies what a pointer parameter points to back to COBOL
+/// after the call.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Direction {
+    In,
+    InOut,
+}
+
+impl Direction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Direction::In => "in",
+            Direction::InOut => "inout",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Direction> {
+        match name {
+            "in" => Some(Direction::In),
+            "inout" => Some(Direction::InOut),
+            _ => None,
+        }
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct CParameter {
@@ -8,48 +48,295 @@ pub struct CParameter {
     pub pointer_depth: u32,
     pub type_size: u32,
     pub java_type: PossibleJavaType,
+    pub handle: bool,
+    pub release_handle: bool,
+    pub callback: Option<CCallback>,
+    /// The dimensions of an array parameter, e.g. `["3", "4"]` for `int m[3][4]`.
+    /// The array decays to a pointer, and `type_size` is the size of the whole array.
+    pub array_dimensions: Vec<String>,
+    /// Qualifiers of the type the parameter (or its pointer) refers to, as in `const char *`.
+    pub is_const: bool,
+    pub is_volatile: bool,
+    pub direction: Direction,
+    /// Whether the type is an enum, which is passed like an `int`.
+    pub is_enum: bool,
+    /// The most characters a string parameter holds, not counting the terminating NUL.
+    pub string_length: u32,
+    /// The type that a `void *` parameter points to, which the schema has to give,
+    /// since `void` has no size. It is passed as the bytes of one such value.
+    pub element_type: Option<String>,
 }
 
 impl CParameter {
+    /// A parameter of `type_name`, which may still have qualifiers such as `const`.
+    /// A `const T*` parameter is only read by the C function, so it is not copied back.
     pub fn new(var_name: &str, type_name: &str, pointer_depth: u32, type_size: u32) -> Self {
-        let java_type = Self::convert_to_java_type(type_name);
+        let words: Vec<&str> = type_name.split_whitespace().collect();
+        let is_const = words.contains(&"const");
+        let is_volatile = words.contains(&"volatile");
+        let type_name = words
+            .into_iter()
+            .filter(|w| !TYPE_QUALIFIERS.contains(w))
+            .collect::<Vec<&str>>()
+            .join(" ");
+        let java_type = Self::convert_to_java_type(&type_name);
         Self {
             var_name: var_name.to_string(),
-            type_name: type_name.to_string(),
+            type_name,
             pointer_depth,
             type_size,
             java_type,
+            handle: false,
+            release_handle: false,
+            callback: None,
+            array_dimensions: Vec::new(),
+            is_const,
+            is_volatile,
+            direction: if is_const && pointer_depth == 1 {
+                Direction::In
+            } else {
+                Direction::InOut
+            },
+            is_enum: false,
+            string_length: 0,
+            element_type: None,
+        }
+    }
+
+    /// A function pointer parameter. It takes no COBOL argument; the C function
+    /// receives a trampoline that runs `callback.program` instead.
+    pub fn new_callback(var_name: &str, callback: CCallback) -> Self {
+        Self {
+            var_name: var_name.to_string(),
+            type_name: callback.return_type.to_string(),
+            pointer_depth: 1,
+            type_size: 0,
+            java_type: PossibleJavaType::Callback,
+            handle: false,
+            release_handle: false,
+            callback: Some(callback),
+            array_dimensions: Vec::new(),
+            is_const: false,
+            is_volatile: false,
+            direction: Direction::InOut,
+            is_enum: false,
+            string_length: 0,
+            element_type: None,
+        }
+    }
+
+    /// Pass the pointer as a token of the Java-side handle registry.
+    /// `T*` parameters take a token from COBOL, `T**` parameters hand a new token back.
+    pub fn set_handle(&mut self, release_handle: bool) {
+        self.handle = true;
+        self.release_handle = release_handle;
+        self.java_type = if self.pointer_depth == 2 {
+            PossibleJavaType::HandleOut
+        } else {
+            PossibleJavaType::Handle
+        };
+    }
+
+    /// Pass the enum value as an `int`.
+    pub fn set_enum(&mut self) {
+        self.is_enum = true;
+        self.java_type = PossibleJavaType::Int;
+    }
+
+    /// Whether the parameter is a plain `char *`, which a function takes as a string.
+    pub fn is_char_pointer(&self) -> bool {
+        self.type_name == "char" && self.pointer_depth == 1 && !self.is_array() && !self.handle
+    }
+
+    /// Pass the `char *` as a NUL-terminated string of at most `string_length` characters.
+    pub fn set_string(&mut self, string_length: u32) {
+        self.java_type = PossibleJavaType::CString;
+        self.string_length = string_length;
+    }
+
+    /// Whether the parameter is a `void *` that is not passed as a handle. A callback
+    /// returning `void` keeps its return type as the type name, so it is not one.
+    pub fn is_void_pointer(&self) -> bool {
+        self.type_name == "void"
+            && self.pointer_depth == 1
+            && !self.is_array()
+            && !self.handle
+            && self.callback.is_none()
+    }
+
+    /// The type of the value that the glue keeps for a pointer parameter,
+    /// e.g. the `element_type` of a `void *`.
+    pub fn pointee_type_name(&self) -> &str {
+        self.element_type.as_deref().unwrap_or(&self.type_name)
+    }
+
+    /// Pass the whole array as bytes, as the elements are laid out in C.
+    pub fn set_array_dimensions(&mut self, array_dimensions: Vec<String>) {
+        if !array_dimensions.is_empty() {
+            self.java_type = PossibleJavaType::ByteArray;
+        }
+        self.array_dimensions = array_dimensions;
+    }
+
+    pub fn is_array(&self) -> bool {
+        !self.array_dimensions.is_empty()
+    }
+
+    /// The dimensions as they are written in a declarator, e.g. `[3][4]`.
+    pub fn array_declarator_suffix(&self) -> String {
+        self.array_dimensions
+            .iter()
+            .map(|d| format!("[{}]", d))
+            .collect()
+    }
+
+    /// Whether the glue copies the data back to COBOL after the call.
+    pub fn copies_back(&self) -> bool {
+        self.direction == Direction::InOut
+    }
+
+    /// Whether the C function may write a primitive value through the pointer,
+    /// which the glue then copies back to COBOL.
+    pub fn copies_back_value(&self) -> bool {
+        self.java_type.primitive_name().is_some()
+            && self.pointer_depth == 1
+            && !self.is_array()
+            && self.copies_back()
+    }
+
+    /// The type name with its qualifiers, e.g. `const char`.
+    pub fn qualified_type_name(&self) -> String {
+        let mut s = String::new();
+        if self.is_const {
+            s += "const ";
         }
+        if self.is_volatile {
+            s += "volatile ";
+        }
+        s + &self.type_name
+    }
+
+    /// The type of the whole array, e.g. `int[3][4]`.
+    pub fn array_type_name(&self) -> String {
+        format!(
+            "{}{}{}",
+            self.qualified_type_name(),
+            "*".repeat(self.pointer_depth as usize - 1),
+            self.array_declarator_suffix()
+        )
     }
 
+    pub fn pointer_type_name(&self) -> String {
+        if let Some(callback) = &self.callback {
+            return callback.pointer_type_name();
+        }
+        if self.is_array() {
+            return self.array_type_name();
+        }
+        format!(
+            "{}{}",
+            self.qualified_type_name(),
+            "*".repeat(self.pointer_depth as usize)
+        )
+    }
+
+    /// The parameter as it is declared in a C function, e.g. `const char *name`,
+    /// `int m[3][4]` or `int (*compare)(int, int)`.
+    pub fn declaration(&self) -> String {
+        if let Some(callback) = &self.callback {
+            return callback.pointer_type_name().replacen(
+                "(*)",
+                &format!("(*{})", self.var_name),
+                1,
+            );
+        }
+        if self.is_array() {
+            return format!(
+                "{}{} {}{}",
+                self.qualified_type_name(),
+                "*".repeat(self.pointer_depth as usize - 1),
+                self.var_name,
+                self.array_declarator_suffix()
+            );
+        }
+        format!("{} {}", self.pointer_type_name(), self.var_name)
+    }
+
+    /// The pointer depth and name of a parameter declarator. Abstract declarators,
+    /// as in `int f(char *)`, have no name, so the name is empty. Array declarators
+    /// decay to one pointer however many dimensions they have.
     pub fn get_pointer_depth_and_var_name<'a>(
         source_code: &'a str,
         pointer_node: Node<'a>,
     ) -> (u32, String) {
         let mut pointer_depth = 0;
+        let mut array = false;
         let mut current_node = pointer_node;
         while let Some(child_node) = current_node.child_by_field_name("declarator") {
-            pointer_depth += 1;
+            if current_node.kind() == "array_declarator" {
+                array = true;
+            } else {
+                pointer_depth += 1;
+            }
             current_node = child_node
         }
+        if array {
+            pointer_depth += 1;
+        }
+        if current_node.kind() == "abstract_pointer_declarator" {
+            return (pointer_depth + 1, String::new());
+        }
         (
             pointer_depth,
             source_code[current_node.range().start_byte..current_node.range().end_byte].to_string(),
         )
     }
 
-    pub fn is_primitive_type(&self) -> bool {
-        match self.type_name.as_str() {
-            "int" => true,
-            "unsigned int" => true,
-            "char" => true,
-            "unsigned char" => true,
-            "short" => true,
-            "unsigned short" => true,
-            _ => false,
+    /// The dimensions of the array declarators in the order they are written.
+    /// The dimension of `int v[]` is empty.
+    pub fn get_array_dimensions(source_code: &str, declarator_node: Node) -> Vec<String> {
+        let mut array_dimensions = Vec::new();
+        let mut current_node = declarator_node;
+        loop {
+            if current_node.kind() == "array_declarator" {
+                let dimension = match current_node.child_by_field_name("size") {
+                    Some(size_node) => source_code
+                        [size_node.range().start_byte..size_node.range().end_byte]
+                        .to_string(),
+                    None => String::new(),
+                };
+                array_dimensions.insert(0, dimension);
+            }
+            current_node = unwrap_some_or!(
+                current_node.child_by_field_name("declarator"),
+                return array_dimensions
+            );
+        }
+    }
+
+    /// Name each unnamed parameter after its position, e.g. `arg1`, so that the names
+    /// are the same on every run. Underscores are added while another parameter has the name.
+    pub fn name_unnamed_parameters(parameters: &mut [CParameter]) {
+        for index in 0..parameters.len() {
+            if !parameters[index].var_name.is_empty() {
+                continue;
+            }
+            let mut var_name = format!("arg{}", index);
+            while parameters.iter().any(|p| p.var_name == var_name) {
+                var_name.push('_');
+            }
+            parameters[index].var_name = var_name;
         }
     }
 
+    pub fn is_primitive_type(&self) -> bool {
+        self.is_enum
+            || matches!(
+                self.type_name.as_str(),
+                "int" | "unsigned int" | "char" | "unsigned char" | "short" | "unsigned short"
+            )
+    }
+
     fn convert_to_java_type(type_name: &str) -> PossibleJavaType {
         match type_name {
             "int" => PossibleJavaType::Int,
@@ -0,0 +1,106 @@
+use crate::cparam::TYPE_QUALIFIERS;
+
+/// Where the bits of a bitfield are in the storage unit that holds them. The unit is
+/// the smallest integer that covers the bits, and it is the `offset` and `size` of the
+/// field. The info program finds the bits by setting the field in a zeroed struct.
+#[derive(Clone, Debug)]
+pub struct BitField {
+    /// The number of bits below the field when the unit is read as a native integer.
+    pub bit_offset: u32,
+    pub bit_width: u32,
+    pub signed: bool,
+}
+
+impl BitField {
+    pub fn new() -> Self {
+        Self {
+            bit_offset: 0,
+            bit_width: 0,
+            signed: false,
+        }
+    }
+}
+
+/// A member of a struct or union. The offset and size are resolved by the C compiler
+/// when the info program runs.
+#[derive(Clone, Debug)]
+pub struct CField {
+    pub name: String,
+    /// The type without its qualifiers and pointers, e.g. `char` for `const char *`.
+    pub type_name: String,
+    pub pointer_depth: u32,
+    /// The dimensions of an array field. They are written as in the header until
+    /// the info program prints the number of elements of each.
+    pub array_dimensions: Vec<String>,
+    pub offset: u32,
+    pub size: u32,
+    pub bitfield: Option<BitField>,
+    /// Whether the type is an enum, which is stored like an `int`.
+    pub is_enum: bool,
+    /// The members of a struct or union defined in place, as in `union { int i; } u;`.
+    /// Their offsets are from the start of the field, or of its first element.
+    /// An anonymous member, whose members belong to the enclosing struct, has no name.
+    pub members: Option<CStruct>,
+}
+
+impl CField {
+    pub fn new(name: &str, type_name: &str, pointer_depth: u32) -> Self {
+        Self {
+            name: name.to_string(),
+            type_name: type_name
+                .split_whitespace()
+                .filter(|w| !TYPE_QUALIFIERS.contains(w))
+                .collect::<Vec<&str>>()
+                .join(" "),
+            pointer_depth,
+            array_dimensions: Vec::new(),
+            offset: 0,
+            size: 0,
+            bitfield: None,
+            is_enum: false,
+            members: None,
+        }
+    }
+
+    pub fn is_array(&self) -> bool {
+        !self.array_dimensions.is_empty()
+    }
+
+    pub fn is_anonymous(&self) -> bool {
+        self.name.is_empty()
+    }
+}
+
+/// A struct or union definition of the C header, or the members of one defined in place.
+#[derive(Clone, Debug)]
+pub struct CStruct {
+    /// The names that refer to the type, e.g. `struct point` and `point_t`.
+    /// Members defined in place have none.
+    pub type_names: Vec<String>,
+    /// Whether all fields start at offset 0.
+    pub is_union: bool,
+    pub size: u32,
+    pub fields: Vec<CField>,
+}
+
+impl CStruct {
+    pub fn new(is_union: bool) -> Self {
+        Self {
+            type_names: Vec::new(),
+            is_union,
+            size: 0,
+            fields: Vec::new(),
+        }
+    }
+
+    /// The name the info program uses for the type, e.g. in `sizeof(struct point)`.
+    pub fn c_type_name(&self) -> &str {
+        &self.type_names[0]
+    }
+
+    pub fn has_bitfields(&self) -> bool {
+        self.fields
+            .iter()
+            .any(|f| f.bitfield.is_some() || f.members.as_ref().is_some_and(|m| m.has_bitfields()))
+    }
+}
@@ -0,0 +1,496 @@
+use crate::ccallback::CCallback;
+use crate::cfunc::CFunction;
+use crate::cparam::CParameter;
+use crate::java_run;
+use crate::java_type::PossibleJavaType;
+use crate::BoundsPolicy;
+
+/// The name of the class that `FFM_HELPER_CLASS` declares.
+pub static FFM_HELPER_CLASS_NAME: &str = "CGlueForeign";
+
+/// Helpers shared by the classes generated for the `ffm` backend. They use the
+/// final `java.lang.foreign` API, so the classes need JDK 22 or later.
+/// The C library is looked up from the `oc4j.c.glue.library` system property
+/// or the `OC4J_C_GLUE_LIBRARY` environment variable, and otherwise from the
+/// libraries loaded by `System.loadLibrary`.
+pub static FFM_HELPER_CLASS: &str = r#"import java.lang.foreign.Arena;
+import java.lang.foreign.FunctionDescriptor;
+import java.lang.foreign.Linker;
+import java.lang.foreign.MemoryLayout;
+import java.lang.foreign.MemorySegment;
+import java.lang.foreign.StructLayout;
+import java.lang.foreign.SymbolLookup;
+import java.lang.foreign.ValueLayout;
+import java.lang.invoke.MethodHandle;
+import java.lang.invoke.VarHandle;
+import java.util.ArrayList;
+import java.util.List;
+
+final class CGlueForeign {
+    static final Linker LINKER = Linker.nativeLinker();
+    static final StructLayout CAPTURE_STATE_LAYOUT = Linker.Option.captureStateLayout();
+    private static final VarHandle ERRNO =
+        CAPTURE_STATE_LAYOUT.varHandle(MemoryLayout.PathElement.groupElement("errno"));
+    private static SymbolLookup lookup = null;
+
+    private CGlueForeign() {
+    }
+
+    private static synchronized SymbolLookup lookup() {
+        if (lookup == null) {
+            String library = System.getProperty("oc4j.c.glue.library", System.getenv("OC4J_C_GLUE_LIBRARY"));
+            if (library == null) {
+                lookup = SymbolLookup.loaderLookup().or(LINKER.defaultLookup());
+            } else {
+                lookup = SymbolLookup.libraryLookup(library, Arena.global()).or(LINKER.defaultLookup());
+            }
+        }
+        return lookup;
+    }
+
+    static MethodHandle downcall(String name, FunctionDescriptor descriptor, boolean captureErrno, int firstVariadicArg) {
+        MemorySegment symbol = lookup().find(name)
+            .orElseThrow(() -> new CGlueException("C function not found: " + name, 0, 0));
+        List<Linker.Option> options = new ArrayList<>();
+        if (captureErrno) {
+            options.add(Linker.Option.captureCallState("errno"));
+        }
+        if (firstVariadicArg >= 0) {
+            options.add(Linker.Option.firstVariadicArg(firstVariadicArg));
+        }
+        return LINKER.downcallHandle(symbol, descriptor, options.toArray(new Linker.Option[0]));
+    }
+
+    static int errno(MemorySegment state) {
+        return (int) ERRNO.get(state, 0L);
+    }
+
+    static MemorySegment copyIn(Arena arena, String name, byte[] bytes, long size, boolean exceptionPolicy) {
+        if (exceptionPolicy && bytes.length < size) {
+            throw new CGlueException(name + " is shorter than " + size + " bytes", 0, 0);
+        }
+        MemorySegment segment = arena.allocate(size);
+        MemorySegment.copy(bytes, 0, segment, ValueLayout.JAVA_BYTE, 0, (int) Math.min(bytes.length, size));
+        return segment;
+    }
+
+    static MemorySegment copyInString(Arena arena, byte[] bytes, long length) {
+        MemorySegment segment = arena.allocate(length + 1);
+        MemorySegment.copy(bytes, 0, segment, ValueLayout.JAVA_BYTE, 0, (int) Math.min(bytes.length, length));
+        return segment;
+    }
+
+    static void copyOut(MemorySegment segment, byte[] bytes) {
+        MemorySegment.copy(segment, ValueLayout.JAVA_BYTE, 0, bytes, 0, (int) Math.min(bytes.length, segment.byteSize()));
+    }
+
+    static byte[] toBytes(MemorySegment pointer, long size) {
+        if (pointer.equals(MemorySegment.NULL)) {
+            return new byte[(int) size];
+        }
+        return pointer.reinterpret(size).toArray(ValueLayout.JAVA_BYTE);
+    }
+
+    static void fromBytes(MemorySegment pointer, byte[] bytes) {
+        if (!pointer.equals(MemorySegment.NULL)) {
+            MemorySegment.copy(bytes, 0, pointer.reinterpret(bytes.length), ValueLayout.JAVA_BYTE, 0, bytes.length);
+        }
+    }
+}
+"#;
+
+/// The Java primitive type and value layout of a primitive C type.
+fn primitive(java_type: &PossibleJavaType) -> Option<(&'static str, &'static str)> {
+    match java_type {
+        PossibleJavaType::Byte => Some(("byte", "ValueLayout.JAVA_BYTE")),
+        PossibleJavaType::Short => Some(("short", "ValueLayout.JAVA_SHORT")),
+        PossibleJavaType::Int => Some(("int", "ValueLayout.JAVA_INT")),
+        _ => None,
+    }
+}
+
+fn parameter_layout(p: &CParameter) -> Result<&'static str, String> {
+    if p.pointer_depth > 0 {
+        return Ok("ValueLayout.ADDRESS");
+    }
+    match primitive(&p.java_type) {
+        Some((_, layout)) => Ok(layout),
+        None => Err(format!(
+            "{} is passed by value, which the ffm backend does not support",
+            p.var_name
+        )),
+    }
+}
+
+fn return_layout(return_type: &str) -> Result<Option<&'static str>, String> {
+    if return_type == "void" {
+        return Ok(None);
+    }
+    if return_type.ends_with('*') {
+        return Ok(Some("ValueLayout.ADDRESS"));
+    }
+    match primitive(&CParameter::new("", return_type, 0, 0).java_type) {
+        Some((_, layout)) => Ok(Some(layout)),
+        None => Err(format!(
+            "return type {} is not supported by the ffm backend",
+            return_type
+        )),
+    }
+}
+
+/// The descriptor of a C function. Variadic arguments from `first_variadic_index` on
+/// are promoted, so `char` and `short` are passed as `int`.
+fn function_descriptor(
+    return_type: &str,
+    parameters: &[CParameter],
+    first_variadic_index: Option<usize>,
+) -> Result<String, String> {
+    let mut layouts = Vec::new();
+    for (index, p) in parameters.iter().enumerate() {
+        let layout = parameter_layout(p)?;
+        let promoted = first_variadic_index.is_some_and(|first| index >= first)
+            && matches!(layout, "ValueLayout.JAVA_BYTE" | "ValueLayout.JAVA_SHORT");
+        layouts.push(if promoted {
+            "ValueLayout.JAVA_INT"
+        } else {
+            layout
+        });
+    }
+    Ok(match return_layout(return_type)? {
+        None => format!("FunctionDescriptor.ofVoid({})", layouts.join(", ")),
+        Some(layout) if layouts.is_empty() => format!("FunctionDescriptor.of({})", layout),
+        Some(layout) => format!("FunctionDescriptor.of({}, {})", layout, layouts.join(", ")),
+    })
+}
+
+fn java_carrier(p: &CParameter) -> &'static str {
+    if p.pointer_depth > 0 {
+        return "MemorySegment";
+    }
+    primitive(&p.java_type).map_or("MemorySegment", |(java, _)| java)
+}
+
+/// A static method that the C function calls through an upcall stub.
+/// It runs the COBOL program of `callback` with arguments marshalled like the JNI trampolines do.
+fn get_callback_method(param: &CParameter, callback: &CCallback) -> Result<String, String> {
+    let mut s = "".to_string();
+    let mut java_parameters = Vec::new();
+    for p in callback.parameters.iter() {
+        if p.pointer_depth == 0 && primitive(&p.java_type).is_none() {
+            return Err(format!(
+                "callback parameter {} is passed by value, which the ffm backend does not support",
+                p.var_name
+            ));
+        }
+        java_parameters.push(format!("{} {}", java_carrier(p), p.var_name));
+    }
+    let java_return_type = match return_layout(&callback.return_type)? {
+        None => "void",
+        Some(_) => primitive(&CParameter::new("", &callback.return_type, 0, 0).java_type)
+            .map_or("MemorySegment", |(java, _)| java),
+    };
+    let default_return = match java_return_type {
+        "void" => "return;".to_string(),
+        "int" => "return 0;".to_string(),
+        "MemorySegment" => "return MemorySegment.NULL;".to_string(),
+        java => format!("return ({}) 0;", java),
+    };
+    s += &format!(
+        "  private static {} callback_{}({}) {{\n",
+        java_return_type,
+        param.var_name,
+        java_parameters.join(", ")
+    );
+    s += "    if (callbackException != null) {\n";
+    s += &format!("      {}\n", default_return);
+    s += "    }\n";
+    s += "    try {\n";
+    s += &format!(
+        "      byte[][] args = new byte[{}][];\n",
+        callback.parameters.len()
+    );
+    for (index, p) in callback.parameters.iter().enumerate() {
+        match (primitive(&p.java_type), p.pointer_depth) {
+            (Some(_), 0) => {
+                s += &format!(
+                    "      args[{}] = {};\n",
+                    index,
+                    p.java_type.value_to_big_endian_bytes(&p.var_name)
+                );
+            }
+            (Some((_, layout)), _) => {
+                s += &format!(
+                    "      args[{}] = {}.equals(MemorySegment.NULL) ? new byte[(int) {}.byteSize()] : {};\n",
+                    index,
+                    p.var_name,
+                    layout,
+                    p.java_type.value_to_big_endian_bytes(&format!("{}.reinterpret({}.byteSize()).get({}, 0)", p.var_name, layout, layout)
+                    )
+                );
+            }
+            (None, _) => {
+                s += &format!(
+                    "      args[{}] = CGlueForeign.toBytes({}, {});\n",
+                    index, p.var_name, p.type_size
+                );
+            }
+        }
+    }
+    s += &format!(
+        "      int result = callProgram(\"{}\", args);\n",
+        callback.program
+    );
+    for (index, p) in callback.parameters.iter().enumerate() {
+        match (primitive(&p.java_type), p.pointer_depth) {
+            (_, 0) => {}
+            _ if !p.copies_back() => {}
+            (Some((_, layout)), _) => {
+                s += &format!("      if (!{}.equals(MemorySegment.NULL)) {{\n", p.var_name);
+                s += &format!(
+                    "        {}.reinterpret({}.byteSize()).set({}, 0, {});\n",
+                    p.var_name,
+                    layout,
+                    layout,
+                    p.java_type
+                        .big_endian_bytes_to_value(&format!("args[{}]", index))
+                );
+                s += "      }\n";
+            }
+            (None, _) => {
+                s += &format!(
+                    "      CGlueForeign.fromBytes({}, args[{}]);\n",
+                    p.var_name, index
+                );
+            }
+        }
+    }
+    match java_return_type {
+        "void" => {}
+        "int" => s += "      return result;\n",
+        "MemorySegment" => s += "      return MemorySegment.NULL;\n",
+        java => s += &format!("      return ({}) result;\n", java),
+    }
+    // An exception must not leave an upcall, so it is rethrown after the downcall returns.
+    s += "    } catch (CGlueException e) {\n";
+    s += "      callbackException = e;\n";
+    s += "    } catch (Throwable e) {\n";
+    s += &format!(
+        "      callbackException = new CGlueException(\"{}: \" + e, 0, 0);\n",
+        param.var_name
+    );
+    s += "    }\n";
+    s += &format!("    {}\n", default_return);
+    s += "  }\n";
+    Ok(s)
+}
+
+fn method_type(callback: &CCallback) -> String {
+    let mut classes = Vec::new();
+    classes.push(
+        match primitive(&CParameter::new("", &callback.return_type, 0, 0).java_type) {
+            _ if callback.return_type == "void" => "void.class".to_string(),
+            _ if callback.return_type.ends_with('*') => "MemorySegment.class".to_string(),
+            Some((java, _)) => format!("{}.class", java),
+            None => "MemorySegment.class".to_string(),
+        },
+    );
+    for p in callback.parameters.iter() {
+        classes.push(format!("{}.class", java_carrier(p)));
+    }
+    format!("MethodType.methodType({})", classes.join(", "))
+}
+
+/// A class that calls the C function through `java.lang.foreign` instead of a JNI library.
+pub fn get_ffm_java_file_content(
+    c_function: &CFunction,
+    bounds_policy: BoundsPolicy,
+) -> Result<String, String> {
+    java_run::check_symbol(c_function, "ffm")?;
+    let mut s = "".to_string();
+    s += "import java.lang.foreign.*;\n";
+    s += "import java.lang.invoke.MethodHandle;\n";
+    if c_function.has_callback() {
+        s += "import java.lang.invoke.MethodHandles;\n";
+        s += "import java.lang.invoke.MethodType;\n";
+    }
+    s += "import jp.osscons.opensourcecobol.libcobj.data.CobolDataStorage;\n";
+
+    s += &format!(
+        "public class {} extends CobolRunnableCGlue {{\n",
+        c_function.name
+    );
+    s += &format!(
+        "  private static final FunctionDescriptor DESCRIPTOR = {};\n",
+        function_descriptor(
+            c_function.java_return_type_name(),
+            &c_function.parameters,
+            c_function.first_variadic_index()
+        )?
+    );
+    s += "  private static MethodHandle handle = null;\n";
+    if c_function.has_callback() {
+        s += "  private static CGlueException callbackException = null;\n";
+        for param in c_function.parameters.iter() {
+            if let Some(callback) = &param.callback {
+                s += &get_callback_method(param, callback)?;
+            }
+        }
+    }
+
+    s += &java_run::get_run_prelude(c_function);
+    s += &java_run::get_run_try(c_function, Some("Arena arena = Arena.ofConfined()"));
+    let storage_indices = c_function.storage_indices();
+    s += "      if (handle == null) {\n";
+    s += &format!(
+        "        handle = CGlueForeign.downcall(\"{}\", DESCRIPTOR, {}, {});\n",
+        c_function.c_name(),
+        c_function.has_error_check(),
+        c_function
+            .first_variadic_index()
+            .map_or(-1, |index| index as i64)
+    );
+    s += "      }\n";
+
+    let mut arguments = Vec::new();
+    if c_function.has_error_check() {
+        s +=
+            "      MemorySegment errnoState = arena.allocate(CGlueForeign.CAPTURE_STATE_LAYOUT);\n";
+        arguments.push("errnoState".to_string());
+    }
+    for (p, i) in c_function.parameters.iter().zip(storage_indices.iter()) {
+        match (&p.java_type, p.pointer_depth) {
+            (PossibleJavaType::Callback, _) => {
+                let callback = p.callback.as_ref().unwrap();
+                s += &format!(
+                    "      MemorySegment {}Segment = CGlueForeign.LINKER.upcallStub(\n",
+                    p.var_name
+                );
+                s += &format!(
+                    "        MethodHandles.lookup().findStatic({}.class, \"callback_{}\", {}),\n",
+                    c_function.name,
+                    p.var_name,
+                    method_type(callback)
+                );
+                s += &format!(
+                    "        {}, arena);\n",
+                    function_descriptor(&callback.return_type, &callback.parameters, None)?
+                );
+            }
+            (PossibleJavaType::Handle, _) => {
+                s += &format!(
+                    "      MemorySegment {}Segment = MemorySegment.ofAddress(CGlueHandleRegistry.resolve(storageToInt(argStorages[{}])));\n",
+                    p.var_name, i
+                );
+            }
+            (PossibleJavaType::HandleOut, _) => {
+                s += &format!(
+                    "      MemorySegment {}Segment = arena.allocate(ValueLayout.ADDRESS);\n",
+                    p.var_name
+                );
+            }
+            (PossibleJavaType::ByteArray, 0) => {
+                return Err(format!(
+                    "{} is passed by value, which the ffm backend does not support",
+                    p.var_name
+                ));
+            }
+            (PossibleJavaType::ByteArray, _) => {
+                s += &format!(
+                    "      MemorySegment {}Segment = CGlueForeign.copyIn(arena, \"{}: argument {}\", {}, {}, {});\n",
+                    p.var_name,
+                    c_function.name,
+                    p.var_name,
+                    p.var_name,
+                    p.type_size,
+                    matches!(bounds_policy, BoundsPolicy::Exception)
+                );
+            }
+            // The segment is zero-filled, so the text ends with a NUL however long it is.
+            (PossibleJavaType::CString, _) => {
+                s += &format!(
+                    "      MemorySegment {}Segment = CGlueForeign.copyInString(arena, {}, {});\n",
+                    p.var_name, p.var_name, p.string_length
+                );
+            }
+            (java_type, 0) => {
+                arguments.push(format!(
+                    "{}(argStorages[{}])",
+                    java_run::storage_accessor(java_type),
+                    i
+                ));
+                continue;
+            }
+            (java_type, _) => {
+                let (_, layout) = primitive(java_type).unwrap();
+                let accessor = java_run::storage_accessor(java_type);
+                s += &format!(
+                    "      MemorySegment {}Segment = arena.allocate({});\n",
+                    p.var_name, layout
+                );
+                s += &format!(
+                    "      {}Segment.set({}, 0, {}(argStorages[{}]));\n",
+                    p.var_name, layout, accessor, i
+                );
+            }
+        }
+        arguments.push(format!("{}Segment", p.var_name));
+    }
+
+    let return_carrier = match return_layout(c_function.java_return_type_name())? {
+        None => None,
+        Some("ValueLayout.ADDRESS") => Some("MemorySegment"),
+        Some(_) => {
+            primitive(&CParameter::new("", c_function.java_return_type_name(), 0, 0).java_type)
+                .map(|(java, _)| java)
+        }
+    };
+    s += "      ";
+    if let (Some(carrier), true) = (return_carrier, c_function.uses_return_value()) {
+        s += &format!("{} result = ({}) ", carrier, carrier);
+    }
+    s += &format!("handle.invoke({});\n", arguments.join(", "));
+    if c_function.has_error_check() {
+        s += "      int errno = CGlueForeign.errno(errnoState);\n";
+    }
+    if c_function.has_callback() {
+        s += &java_run::get_callback_exception_check();
+    }
+    for (p, i) in c_function.parameters.iter().zip(storage_indices.iter()) {
+        match p.java_type {
+            PossibleJavaType::ByteArray | PossibleJavaType::CString if p.copies_back() => {
+                s += &format!(
+                    "      CGlueForeign.copyOut({}Segment, {});\n",
+                    p.var_name, p.var_name
+                );
+            }
+            PossibleJavaType::HandleOut => {
+                s += &format!(
+                    "      intToStorage(argStorages[{}], CGlueHandleRegistry.register({}Segment.get(ValueLayout.ADDRESS, 0).address()));\n",
+                    i, p.var_name
+                );
+            }
+            _ if p.copies_back_value() => {
+                let (java, layout) = primitive(&p.java_type).unwrap();
+                s += &format!(
+                    "      {}ToStorage(argStorages[{}], {}Segment.get({}, 0));\n",
+                    java, i, p.var_name, layout
+                );
+            }
+            _ => {}
+        }
+    }
+    s += &java_run::get_handle_copy_back(c_function);
+    let return_value = match return_carrier {
+        None => "0",
+        Some("MemorySegment") => "result.address()",
+        Some(_) => "result",
+    };
+    s += &java_run::get_error_check(
+        c_function,
+        "result.equals(MemorySegment.NULL)",
+        return_value,
+    );
+    s += &java_run::get_returned_handle(c_function, "result.address()");
+    s += &java_run::get_run_epilogue(c_function, true, "");
+    Ok(s)
+}
@@ -0,0 +1,103 @@
+/// Selects the C functions that `parse_c` generates glue for.
+/// A function is kept when it matches an include pattern or is in the allowlist
+/// (or when neither is given) and does not match an exclude pattern.
+#[derive(Clone, Debug)]
+pub struct FunctionFilter {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub allowlist: Vec<String>,
+}
+
+impl FunctionFilter {
+    pub fn new() -> Self {
+        Self {
+            include: Vec::new(),
+            exclude: Vec::new(),
+            allowlist: Vec::new(),
+        }
+    }
+
+    /// Read an allowlist that has one function name per line.
+    /// Blank lines and lines starting with `#` are ignored.
+    pub fn parse_allowlist(content: &str) -> Vec<String> {
+        content
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| line.to_string())
+            .collect()
+    }
+
+    pub fn accepts(&self, name: &str) -> bool {
+        let selected = (self.include.is_empty() && self.allowlist.is_empty())
+            || self.include.iter().any(|p| glob_match(p, name))
+            || self.allowlist.iter().any(|n| n == name);
+        selected && !self.exclude.iter().any(|p| glob_match(p, name))
+    }
+}
+
+/// Match `name` against a glob where `*` matches any sequence and `?` any one character.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            n = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_match_wildcards() {
+        assert!(glob_match("sql_*", "sql_open"));
+        assert!(glob_match("sql_*", "sql_"));
+        assert!(!glob_match("sql_*", "nosql_open"));
+        assert!(glob_match("get_?", "get_x"));
+        assert!(!glob_match("get_?", "get_xy"));
+        assert!(glob_match("*_free", "list_free"));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+        assert!(!glob_match("a*b*c", "aXbYbZ"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("exact", "exact"));
+        assert!(!glob_match("exact", "exactly"));
+    }
+
+    #[test]
+    fn accepts_includes_allowlist_and_excludes() {
+        let mut filter = FunctionFilter::new();
+        assert!(filter.accepts("anything"));
+
+        filter.include = vec!["list_*".to_string()];
+        filter.allowlist = vec!["open".to_string()];
+        filter.exclude = vec!["*_internal".to_string()];
+        assert!(filter.accepts("list_push"));
+        assert!(filter.accepts("open"));
+        assert!(!filter.accepts("close"));
+        assert!(!filter.accepts("list_internal"));
+    }
+
+    #[test]
+    fn parse_allowlist_skips_blank_lines_and_comments() {
+        assert_eq!(
+            FunctionFilter::parse_allowlist("# functions\nopen\n\n  close  \n#read\n"),
+            vec!["open".to_string(), "close".to_string()]
+        );
+    }
+}
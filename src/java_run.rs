@@ -0,0 +1,192 @@
+use crate::cfunc::{CFunction, ErrorValue};
+use crate::java_type::PossibleJavaType;
+
+/// The method of `CobolRunnableCGlue` that reads a primitive from a COBOL argument.
+pub fn storage_accessor(java_type: &PossibleJavaType) -> &'static str {
+    match java_type {
+        PossibleJavaType::Byte => "storageToByte",
+        PossibleJavaType::Short => "storageToShort",
+        _ => "storageToInt",
+    }
+}
+
+/// A backend other than jni binds the C symbol itself, and only the jni glue
+/// defines a wrapper for a function that has no symbol.
+pub fn check_symbol(c_function: &CFunction, backend_name: &str) -> Result<(), String> {
+    if c_function.needs_wrapper() {
+        return Err(format!(
+            "{} is a static inline function or a macro, which the {} backend does not support",
+            c_function.c_name(),
+            backend_name
+        ));
+    }
+    Ok(())
+}
+
+/// The start of the `run` method that every backend generates. Byte arrays and strings
+/// are copied out of their COBOL arguments before the `try` block, so that they can be
+/// copied back whether the call succeeds or not.
+pub fn get_run_prelude(c_function: &CFunction) -> String {
+    let mut s = "".to_string();
+    s += "  @Override\n";
+    s += "  public int run(CobolDataStorage... argStorages) {\n";
+    for (p, i) in c_function
+        .parameters
+        .iter()
+        .zip(c_function.storage_indices().iter())
+    {
+        if let PossibleJavaType::ByteArray = p.java_type {
+            s += &format!(
+                "    byte[] {} = storageToByteArray(argStorages[{}], {});\n",
+                p.var_name, i, p.type_size
+            );
+        }
+        // A string also takes the byte after its last character, where a NUL may end it.
+        if let PossibleJavaType::CString = p.java_type {
+            s += &format!(
+                "    byte[] {} = storageToByteArray(argStorages[{}], {});\n",
+                p.var_name,
+                i,
+                p.string_length + 1
+            );
+        }
+    }
+    s
+}
+
+/// The start of the `try` block that makes the call, opened with `resources` if any.
+/// A function that returns a handle fails with RETURN-CODE -1 when the caller passes
+/// no argument for it, which is checked before the call, as the pointer would be lost.
+pub fn get_run_try(c_function: &CFunction, resources: Option<&str>) -> String {
+    let mut s = "".to_string();
+    s += "    int returnCode = 0;\n";
+    match resources {
+        Some(resources) => s += &format!("    try ({}) {{\n", resources),
+        None => s += "    try {\n",
+    }
+    if c_function.return_handle {
+        s += &format!(
+            "      if (argStorages.length <= {}) {{\n",
+            c_function.num_of_arguments()
+        );
+        s += &format!(
+            "        throw new CGlueException(\"{}: no argument receives the returned handle\", 0, 0);\n",
+            c_function.name
+        );
+        s += "      }\n";
+    }
+    s
+}
+
+/// Rethrows the exception that a callback recorded while C was running.
+pub fn get_callback_exception_check() -> String {
+    let mut s = "".to_string();
+    s += "      if (callbackException != null) {\n";
+    s += "        CGlueException e = callbackException;\n";
+    s += "        callbackException = null;\n";
+    s += "        throw e;\n";
+    s += "      }\n";
+    s
+}
+
+/// Releases the handles that the function frees and clears their COBOL arguments.
+pub fn get_handle_copy_back(c_function: &CFunction) -> String {
+    let mut s = "".to_string();
+    for (p, i) in c_function
+        .parameters
+        .iter()
+        .zip(c_function.storage_indices().iter())
+    {
+        if p.release_handle {
+            s += &format!(
+                "      CGlueHandleRegistry.release(storageToInt(argStorages[{}]));\n",
+                i
+            );
+            s += &format!("      intToStorage(argStorages[{}], 0);\n", i);
+        }
+    }
+    s
+}
+
+/// Registers the returned pointer, a Java `long` expression, and stores its token.
+pub fn get_returned_handle(c_function: &CFunction, address: &str) -> String {
+    if !c_function.return_handle {
+        return "".to_string();
+    }
+    format!(
+        "      intToStorage(argStorages[{}], CGlueHandleRegistry.register({}));\n",
+        c_function.num_of_arguments(),
+        address
+    )
+}
+
+/// The condition on `result` and `errno` under which the call failed.
+/// `null_condition` tests a returned pointer for NULL.
+pub fn get_error_condition(c_function: &CFunction, null_condition: &str) -> String {
+    let mut conditions = Vec::new();
+    for error_value in c_function.error_values.iter() {
+        conditions.push(match error_value {
+            ErrorValue::Negative => "result < 0".to_string(),
+            ErrorValue::Null => null_condition.to_string(),
+            ErrorValue::Value(value) => format!("result == {}", value),
+        });
+    }
+    if conditions.is_empty() {
+        conditions.push("errno != 0".to_string());
+    }
+    conditions.join(" || ")
+}
+
+/// Throws a CGlueException when the call failed. `return_value` is the result as a `long`.
+pub fn get_error_check(c_function: &CFunction, null_condition: &str, return_value: &str) -> String {
+    if !c_function.has_error_check() {
+        return "".to_string();
+    }
+    let mut s = "".to_string();
+    s += &format!(
+        "      if ({}) {{\n",
+        get_error_condition(c_function, null_condition)
+    );
+    s += &format!(
+        "        throw new CGlueException(\"{}: \" + (errno != 0 ? \"errno \" + errno : \"error return value\"), errno, {});\n",
+        c_function.name, return_value
+    );
+    s += "      }\n";
+    s
+}
+
+/// The end of the `try` block through the end of the class. Any other `Throwable`
+/// is reported like a CGlueException when `catch_throwable` is set. Byte arrays
+/// and strings are copied back after the `try` block, so that COBOL sees what C
+/// wrote even when the call failed.
+pub fn get_run_epilogue(c_function: &CFunction, catch_throwable: bool, copy_back: &str) -> String {
+    let mut s = "".to_string();
+    s += "    } catch (CGlueException e) {\n";
+    s += "      returnCode = handleCGlueException(e);\n";
+    if catch_throwable {
+        s += "    } catch (Throwable e) {\n";
+        s += &format!(
+            "      returnCode = handleCGlueException(new CGlueException(\"{}: \" + e, 0, 0));\n",
+            c_function.name
+        );
+    }
+    s += "    }\n";
+    for (p, i) in c_function
+        .parameters
+        .iter()
+        .zip(c_function.storage_indices().iter())
+    {
+        if matches!(
+            p.java_type,
+            PossibleJavaType::ByteArray | PossibleJavaType::CString
+        ) && p.copies_back()
+        {
+            s += &format!("    bytesToStorage(argStorages[{}], {});\n", i, p.var_name);
+        }
+    }
+    s += copy_back;
+    s += "    return returnCode;\n";
+    s += "  }\n";
+    s += "}\n";
+    s
+}
@@ -4,4 +4,48 @@ pub enum PossibleJavaType {
     Short,
     Int,
     ByteArray,
+    /// A `char *` string, passed as bytes that the glue copies to a NUL-terminated buffer.
+    CString,
+    Handle,
+    HandleOut,
+    Callback,
+}
+
+impl PossibleJavaType {
+    /// The Java primitive type that carries a primitive C value.
+    pub fn primitive_name(&self) -> Option<&'static str> {
+        match self {
+            PossibleJavaType::Byte => Some("byte"),
+            PossibleJavaType::Short => Some("short"),
+            PossibleJavaType::Int => Some("int"),
+            _ => None,
+        }
+    }
+
+    /// A Java expression that converts the primitive `value` to COBOL binary bytes.
+    pub fn value_to_big_endian_bytes(&self, value: &str) -> String {
+        match self {
+            PossibleJavaType::Byte => format!("new byte[] {{ {} }}", value),
+            PossibleJavaType::Short => {
+                format!(
+                    "java.nio.ByteBuffer.allocate(2).putShort({}).array()",
+                    value
+                )
+            }
+            PossibleJavaType::Int => {
+                format!("java.nio.ByteBuffer.allocate(4).putInt({}).array()", value)
+            }
+            _ => value.to_string(),
+        }
+    }
+
+    /// A Java expression that converts COBOL binary `bytes` to a primitive value.
+    pub fn big_endian_bytes_to_value(&self, bytes: &str) -> String {
+        match self {
+            PossibleJavaType::Byte => format!("{}[0]", bytes),
+            PossibleJavaType::Short => format!("java.nio.ByteBuffer.wrap({}).getShort()", bytes),
+            PossibleJavaType::Int => format!("java.nio.ByteBuffer.wrap({}).getInt()", bytes),
+            _ => bytes.to_string(),
+        }
+    }
 }
@@ -0,0 +1,514 @@
+use crate::ccallback::CCallback;
+use crate::cfunc::CFunction;
+use crate::cparam::CParameter;
+use crate::java_run;
+use crate::java_type::PossibleJavaType;
+use crate::BoundsPolicy;
+
+/// The name of the JNA `Library` interface that declares every C function of the schema.
+pub static JNA_LIBRARY_NAME: &str = "CGlueLibrary";
+
+/// The name of the `Structure` class that holds the bytes a pointer parameter points to.
+/// Structures are generated only for pointers, so `type_size` is the size of the pointee.
+fn structure_name(p: &CParameter) -> String {
+    let mut name = "C_".to_string();
+    for c in p.pointee_type_name().chars() {
+        name.push(if c.is_ascii_alphanumeric() { c } else { '_' });
+    }
+    for _ in 1..p.pointer_depth {
+        name += "_p";
+    }
+    for dimension in p.array_dimensions.iter() {
+        name += &format!("_{}", dimension);
+    }
+    name
+}
+
+fn primitive_reference(java_type: &PossibleJavaType) -> Option<&'static str> {
+    match java_type {
+        PossibleJavaType::Byte => Some("ByteByReference"),
+        PossibleJavaType::Short => Some("ShortByReference"),
+        PossibleJavaType::Int => Some("IntByReference"),
+        _ => None,
+    }
+}
+
+fn callback_interface_name(c_function: &CFunction, p: &CParameter) -> String {
+    format!("{}_{}", c_function.name, p.var_name)
+}
+
+fn parameter_type(c_function: &CFunction, p: &CParameter) -> Result<String, String> {
+    match (&p.java_type, p.pointer_depth) {
+        (PossibleJavaType::Callback, _) => Ok(format!(
+            "{}.{}",
+            JNA_LIBRARY_NAME,
+            callback_interface_name(c_function, p)
+        )),
+        (PossibleJavaType::Handle, _) | (PossibleJavaType::CString, _) => Ok("Pointer".to_string()),
+        (PossibleJavaType::HandleOut, _) => Ok("PointerByReference".to_string()),
+        (PossibleJavaType::ByteArray, 0) => Err(format!(
+            "{} is passed by value, which the jna backend does not support",
+            p.var_name
+        )),
+        (PossibleJavaType::ByteArray, _) => {
+            Ok(format!("{}.{}", JNA_LIBRARY_NAME, structure_name(p)))
+        }
+        (java_type, 0) => Ok(java_type.primitive_name().unwrap().to_string()),
+        (java_type, 1) => Ok(primitive_reference(java_type).unwrap().to_string()),
+        (_, _) => Err(format!(
+            "{} is a pointer to a pointer, which the jna backend does not support",
+            p.var_name
+        )),
+    }
+}
+
+fn return_type(return_type: &str) -> Result<&'static str, String> {
+    if return_type == "void" {
+        return Ok("void");
+    }
+    if return_type.ends_with('*') {
+        return Ok("Pointer");
+    }
+    match CParameter::new("", return_type, 0, 0)
+        .java_type
+        .primitive_name()
+    {
+        Some(java) => Ok(java),
+        None => Err(format!(
+            "return type {} is not supported by the jna backend",
+            return_type
+        )),
+    }
+}
+
+fn callback_parameter_type(p: &CParameter) -> Result<&'static str, String> {
+    if p.pointer_depth > 0 {
+        return Ok("Pointer");
+    }
+    match p.java_type.primitive_name() {
+        Some(java) => Ok(java),
+        None => Err(format!(
+            "callback parameter {} is passed by value, which the jna backend does not support",
+            p.var_name
+        )),
+    }
+}
+
+fn get_callback_interface(
+    c_function: &CFunction,
+    p: &CParameter,
+    callback: &CCallback,
+) -> Result<String, String> {
+    let mut java_parameters = Vec::new();
+    for param in callback.parameters.iter() {
+        java_parameters.push(format!(
+            "{} {}",
+            callback_parameter_type(param)?,
+            param.var_name
+        ));
+    }
+    let mut s = "".to_string();
+    s += &format!(
+        "  interface {} extends Callback {{\n",
+        callback_interface_name(c_function, p)
+    );
+    s += &format!(
+        "    {} invoke({});\n",
+        return_type(&callback.return_type)?,
+        java_parameters.join(", ")
+    );
+    s += "  }\n";
+    Ok(s)
+}
+
+/// The `Library` interface that JNA binds to the C library, with a `Structure`
+/// class for each pointer type and a `Callback` interface for each callback parameter.
+/// The library is loaded from the `oc4j.c.glue.library` system property or the
+/// `OC4J_C_GLUE_LIBRARY` environment variable, and otherwise from the current process.
+pub fn get_jna_library_file_content(c_functions: &[CFunction]) -> Result<String, String> {
+    let mut s = "".to_string();
+    s += "import com.sun.jna.Callback;\n";
+    s += "import com.sun.jna.Library;\n";
+    s += "import com.sun.jna.Native;\n";
+    s += "import com.sun.jna.Pointer;\n";
+    s += "import com.sun.jna.Structure;\n";
+    s += "import com.sun.jna.ptr.*;\n";
+    s += &format!("public interface {} extends Library {{\n", JNA_LIBRARY_NAME);
+    s += &format!("  {} INSTANCE = Native.load(\n", JNA_LIBRARY_NAME);
+    s += "    System.getProperty(\"oc4j.c.glue.library\", System.getenv(\"OC4J_C_GLUE_LIBRARY\")),\n";
+    s += &format!("    {}.class);\n", JNA_LIBRARY_NAME);
+
+    let mut structures: Vec<(String, u32)> = Vec::new();
+    for c_function in c_functions.iter() {
+        for p in c_function.parameters.iter() {
+            if let (PossibleJavaType::ByteArray, 1..) = (&p.java_type, p.pointer_depth) {
+                let name = structure_name(p);
+                match structures.iter().find(|(n, _)| *n == name) {
+                    Some((_, size)) if *size != p.type_size => {
+                        return Err(format!(
+                            "{} has different sizes ({} and {})",
+                            p.pointee_type_name(),
+                            size,
+                            p.type_size
+                        ))
+                    }
+                    Some(_) => {}
+                    None => structures.push((name, p.type_size)),
+                }
+            }
+        }
+    }
+    for (name, size) in structures.iter() {
+        s += "  @Structure.FieldOrder({\"data\"})\n";
+        s += &format!("  class {} extends Structure {{\n", name);
+        s += &format!("    public byte[] data = new byte[{}];\n", size);
+        s += "  }\n";
+    }
+
+    for c_function in c_functions.iter() {
+        for p in c_function.parameters.iter() {
+            if let Some(callback) = &p.callback {
+                s += &get_callback_interface(c_function, p, callback)?;
+            }
+        }
+    }
+
+    let mut declared: Vec<&str> = Vec::new();
+    for c_function in c_functions.iter() {
+        // The instances of a variadic function share one declaration with `Object...`.
+        if declared.contains(&c_function.c_name()) {
+            continue;
+        }
+        declared.push(c_function.c_name());
+        let num_of_fixed_parameters = c_function
+            .first_variadic_index()
+            .unwrap_or(c_function.parameters.len());
+        let mut java_parameters = Vec::new();
+        for p in c_function.parameters[..num_of_fixed_parameters].iter() {
+            if c_function.variadic && p.callback.is_some() {
+                return Err(format!(
+                    "{} is a callback of the variadic function {}, which the jna backend does not support",
+                    p.var_name,
+                    c_function.c_name()
+                ));
+            }
+            java_parameters.push(format!("{} {}", parameter_type(c_function, p)?, p.var_name));
+        }
+        if c_function.variadic {
+            java_parameters.push("Object... args".to_string());
+        }
+        s += &format!(
+            "  {} {}({});\n",
+            return_type(c_function.java_return_type_name())?,
+            c_function.c_name(),
+            java_parameters.join(", ")
+        );
+    }
+    s += "}\n";
+    Ok(s)
+}
+
+/// A static field holding the callback object, which also keeps it from being
+/// garbage collected while C may still call it.
+fn get_callback_field(
+    c_function: &CFunction,
+    param: &CParameter,
+    callback: &CCallback,
+) -> Result<String, String> {
+    let mut java_parameters = Vec::new();
+    for p in callback.parameters.iter() {
+        java_parameters.push(format!("{} {}", callback_parameter_type(p)?, p.var_name));
+    }
+    let java_return_type = return_type(&callback.return_type)?;
+    let default_return = match java_return_type {
+        "void" => "return;".to_string(),
+        "int" => "return 0;".to_string(),
+        java => format!("return ({}) 0;", java),
+    };
+    let mut s = "".to_string();
+    s += &format!(
+        "  private static final {}.{} {} = new {}.{}() {{\n",
+        JNA_LIBRARY_NAME,
+        callback_interface_name(c_function, param),
+        param.var_name.to_uppercase(),
+        JNA_LIBRARY_NAME,
+        callback_interface_name(c_function, param)
+    );
+    s += &format!(
+        "    public {} invoke({}) {{\n",
+        java_return_type,
+        java_parameters.join(", ")
+    );
+    s += "      if (callbackException != null) {\n";
+    s += &format!("        {}\n", default_return);
+    s += "      }\n";
+    s += "      try {\n";
+    s += &format!(
+        "        byte[][] args = new byte[{}][];\n",
+        callback.parameters.len()
+    );
+    for (index, p) in callback.parameters.iter().enumerate() {
+        match (p.java_type.primitive_name(), p.pointer_depth) {
+            (Some(_), 0) => {
+                s += &format!(
+                    "        args[{}] = {};\n",
+                    index,
+                    p.java_type.value_to_big_endian_bytes(&p.var_name)
+                );
+            }
+            (Some(java), _) => {
+                s += &format!(
+                    "        args[{}] = {} == null ? new byte[{}] : {};\n",
+                    index,
+                    p.var_name,
+                    p.type_size,
+                    p.java_type.value_to_big_endian_bytes(&format!(
+                        "{}.get{}{}(0)",
+                        p.var_name,
+                        java[..1].to_uppercase(),
+                        &java[1..]
+                    ))
+                );
+            }
+            (None, _) => {
+                s += &format!(
+                    "        args[{}] = {} == null ? new byte[{}] : {}.getByteArray(0, {});\n",
+                    index, p.var_name, p.type_size, p.var_name, p.type_size
+                );
+            }
+        }
+    }
+    s += &format!(
+        "        int result = callProgram(\"{}\", args);\n",
+        callback.program
+    );
+    for (index, p) in callback.parameters.iter().enumerate() {
+        match (p.java_type.primitive_name(), p.pointer_depth) {
+            (_, 0) => {}
+            _ if !p.copies_back() => {}
+            (Some(java), _) => {
+                s += &format!("        if ({} != null) {{\n", p.var_name);
+                s += &format!(
+                    "          {}.set{}{}(0, {});\n",
+                    p.var_name,
+                    java[..1].to_uppercase(),
+                    &java[1..],
+                    p.java_type
+                        .big_endian_bytes_to_value(&format!("args[{}]", index))
+                );
+                s += "        }\n";
+            }
+            (None, _) => {
+                s += &format!("        if ({} != null) {{\n", p.var_name);
+                s += &format!(
+                    "          {}.write(0, args[{}], 0, args[{}].length);\n",
+                    p.var_name, index, index
+                );
+                s += "        }\n";
+            }
+        }
+    }
+    match java_return_type {
+        "void" => {}
+        "int" => s += "        return result;\n",
+        java => s += &format!("        return ({}) result;\n", java),
+    }
+    s += "      } catch (CGlueException e) {\n";
+    s += "        callbackException = e;\n";
+    s += "      } catch (Throwable e) {\n";
+    s += &format!(
+        "        callbackException = new CGlueException(\"{}: \" + e, 0, 0);\n",
+        param.var_name
+    );
+    s += "      }\n";
+    s += &format!("      {}\n", default_return);
+    s += "    }\n";
+    s += "  };\n";
+    Ok(s)
+}
+
+/// A class that calls the C function through the JNA `Library` interface instead of a JNI library.
+pub fn get_jna_java_file_content(
+    c_function: &CFunction,
+    bounds_policy: BoundsPolicy,
+) -> Result<String, String> {
+    java_run::check_symbol(c_function, "jna")?;
+    let mut s = "".to_string();
+    s += "import com.sun.jna.Memory;\n";
+    s += "import com.sun.jna.Native;\n";
+    s += "import com.sun.jna.Pointer;\n";
+    s += "import com.sun.jna.ptr.*;\n";
+    s += "import jp.osscons.opensourcecobol.libcobj.data.CobolDataStorage;\n";
+
+    s += &format!(
+        "public class {} extends CobolRunnableCGlue {{\n",
+        c_function.name
+    );
+    if c_function.has_callback() {
+        s += "  private static CGlueException callbackException = null;\n";
+        for param in c_function.parameters.iter() {
+            if let Some(callback) = &param.callback {
+                s += &get_callback_field(c_function, param, callback)?;
+            }
+        }
+    }
+
+    s += &java_run::get_run_prelude(c_function);
+    s += &java_run::get_run_try(c_function, None);
+    let storage_indices = c_function.storage_indices();
+
+    let mut arguments = Vec::new();
+    for (index, (p, i)) in c_function
+        .parameters
+        .iter()
+        .zip(storage_indices.iter())
+        .enumerate()
+    {
+        let java_type = parameter_type(c_function, p)?;
+        match (&p.java_type, p.pointer_depth) {
+            (PossibleJavaType::Callback, _) => {
+                arguments.push(p.var_name.to_uppercase());
+                continue;
+            }
+            (PossibleJavaType::Handle, _) => {
+                s += &format!(
+                    "      long {}Address = CGlueHandleRegistry.resolve(storageToInt(argStorages[{}]));\n",
+                    p.var_name, i
+                );
+                s += &format!(
+                    "      Pointer {}Pointer = {}Address == 0 ? null : new Pointer({}Address);\n",
+                    p.var_name, p.var_name, p.var_name
+                );
+                arguments.push(format!("{}Pointer", p.var_name));
+                continue;
+            }
+            (PossibleJavaType::HandleOut, _) => {
+                s += &format!(
+                    "      {} {}Reference = new {}();\n",
+                    java_type, p.var_name, java_type
+                );
+            }
+            (PossibleJavaType::ByteArray, _) => {
+                s += &format!(
+                    "      {} {}Reference = new {}();\n",
+                    java_type, p.var_name, java_type
+                );
+                if let BoundsPolicy::Exception = bounds_policy {
+                    s += &format!("      if ({}.length < {}) {{\n", p.var_name, p.type_size);
+                    s += &format!(
+                        "        throw new CGlueException(\"{}: argument {} is shorter than {} bytes\", 0, 0);\n",
+                        c_function.name, p.var_name, p.type_size
+                    );
+                    s += "      }\n";
+                }
+                s += &format!(
+                    "      System.arraycopy({}, 0, {}Reference.data, 0, Math.min({}.length, {}));\n",
+                    p.var_name, p.var_name, p.var_name, p.type_size
+                );
+            }
+            // `clear` leaves a NUL after the text, since it is at most `string_length` bytes.
+            (PossibleJavaType::CString, _) => {
+                s += &format!(
+                    "      Memory {}Memory = new Memory({} + 1);\n",
+                    p.var_name, p.string_length
+                );
+                s += &format!("      {}Memory.clear();\n", p.var_name);
+                s += &format!(
+                    "      {}Memory.write(0, {}, 0, Math.min({}.length, {}));\n",
+                    p.var_name, p.var_name, p.var_name, p.string_length
+                );
+                arguments.push(format!("{}Memory", p.var_name));
+                continue;
+            }
+            (java, 0) => {
+                // Variadic arguments are promoted, so `char` and `short` are passed as `int`.
+                let promoted = c_function
+                    .first_variadic_index()
+                    .is_some_and(|first| index >= first)
+                    && !matches!(java, PossibleJavaType::Int);
+                arguments.push(format!(
+                    "{}{}(argStorages[{}])",
+                    if promoted { "(int) " } else { "" },
+                    java_run::storage_accessor(java),
+                    i
+                ));
+                continue;
+            }
+            (java, _) => {
+                s += &format!(
+                    "      {} {}Reference = new {}({}(argStorages[{}]));\n",
+                    java_type,
+                    p.var_name,
+                    java_type,
+                    java_run::storage_accessor(java),
+                    i
+                );
+            }
+        }
+        arguments.push(format!("{}Reference", p.var_name));
+    }
+
+    if c_function.has_error_check() {
+        s += "      Native.setLastError(0);\n";
+    }
+    s += "      ";
+    if c_function.uses_return_value() {
+        s += &format!(
+            "{} result = ",
+            return_type(c_function.java_return_type_name())?
+        );
+    }
+    s += &format!(
+        "{}.INSTANCE.{}({});\n",
+        JNA_LIBRARY_NAME,
+        c_function.c_name(),
+        arguments.join(", ")
+    );
+    if c_function.has_error_check() {
+        s += "      int errno = Native.getLastError();\n";
+    }
+    if c_function.has_callback() {
+        s += &java_run::get_callback_exception_check();
+    }
+    for (p, i) in c_function.parameters.iter().zip(storage_indices.iter()) {
+        match p.java_type {
+            PossibleJavaType::ByteArray if p.copies_back() => {
+                s += &format!(
+                    "      System.arraycopy({}Reference.data, 0, {}, 0, Math.min({}.length, {}));\n",
+                    p.var_name, p.var_name, p.var_name, p.type_size
+                );
+            }
+            PossibleJavaType::CString if p.copies_back() => {
+                s += &format!(
+                    "      {}Memory.read(0, {}, 0, {}.length);\n",
+                    p.var_name, p.var_name, p.var_name
+                );
+            }
+            PossibleJavaType::HandleOut => {
+                s += &format!(
+                    "      intToStorage(argStorages[{}], CGlueHandleRegistry.register(Pointer.nativeValue({}Reference.getValue())));\n",
+                    i, p.var_name
+                );
+            }
+            _ if p.copies_back_value() => {
+                s += &format!(
+                    "      {}ToStorage(argStorages[{}], {}Reference.getValue());\n",
+                    p.java_type, i, p.var_name
+                );
+            }
+            _ => {}
+        }
+    }
+    s += &java_run::get_handle_copy_back(c_function);
+    let return_value = if c_function.returns_void() {
+        "0"
+    } else if c_function.returns_pointer() {
+        "Pointer.nativeValue(result)"
+    } else {
+        "result"
+    };
+    s += &java_run::get_error_check(c_function, "result == null", return_value);
+    s += &java_run::get_returned_handle(c_function, "Pointer.nativeValue(result)");
+    s += &java_run::get_run_epilogue(c_function, true, "");
+    Ok(s)
+}
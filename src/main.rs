@@ -4,17 +4,41 @@ use std::fmt;
 use std::fs;
 use std::fs::File;
 use std::io::Write;
-use tree_sitter::{Parser, Query, QueryCursor};
+use std::process::Command;
+use tree_sitter::{Node, Parser, Query, QueryCursor};
 use unwrap_or::*;
 use yaml_rust::Yaml;
 use yaml_rust::YamlLoader;
 
+mod c_source;
+mod call_check;
+mod ccallback;
+mod cconst;
+mod cenum;
 mod cfunc;
+mod cobol_call;
+mod cobol_data;
+mod cobol_program;
+mod cobol_source;
+mod cobol_wrapper;
 mod cparam;
+mod cstruct;
+mod ffm;
+mod function_filter;
+mod java_run;
 mod java_type;
+mod jna;
 
-use cfunc::CFunction;
-use cparam::CParameter;
+use ccallback::CCallback;
+use cconst::{CConstant, ConstantKind, ConstantValue};
+use cenum::{CEnum, CEnumConstant};
+use cfunc::{CFunction, ErrorValue, FunctionKind};
+use cobol_call::CobolCall;
+use cobol_program::CobolProgram;
+use cobol_source::SourceFormat;
+use cparam::{CParameter, Direction, DEFAULT_STRING_LENGTH};
+use cstruct::{BitField, CField, CStruct};
+use function_filter::FunctionFilter;
 use java_type::PossibleJavaType;
 
 impl fmt::Display for PossibleJavaType {
@@ -23,31 +47,58 @@ impl fmt::Display for PossibleJavaType {
             PossibleJavaType::Byte => write!(f, "byte"),
             PossibleJavaType::Short => write!(f, "short"),
             PossibleJavaType::Int => write!(f, "int"),
-            PossibleJavaType::ByteArray => write!(f, "byte[]"),
+            PossibleJavaType::ByteArray | PossibleJavaType::CString => write!(f, "byte[]"),
+            PossibleJavaType::Handle => write!(f, "long"),
+            PossibleJavaType::HandleOut => write!(f, "long[]"),
+            PossibleJavaType::Callback => write!(f, "CobolRunnable"),
         }
     }
 }
 
+/// Whether a declaration or function definition has internal linkage, so that the
+/// C library has no symbol for it.
+fn is_static(source_code: &str, declaration_node: Node) -> bool {
+    let mut cursor = declaration_node.walk();
+    let is_static = declaration_node.children(&mut cursor).any(|node| {
+        node.kind() == "storage_class_specifier"
+            && &source_code[node.range().start_byte..node.range().end_byte] == "static"
+    });
+    is_static
+}
+
+/// The name of a function that the glue leaves out and the reason.
+type UnsupportedFunction = (String, String);
+
+/// Extract the function prototypes. A prototype that the glue cannot support is left
+/// out and returned with the reason, so that one such function does not keep the
+/// other functions of the header from being generated.
 fn extract_function_declarators(
     c_lang_parser: &mut Parser,
     source_code: &str,
-) -> Option<Vec<CFunction>> {
+) -> Option<(Vec<CFunction>, Vec<UnsupportedFunction>)> {
     let tree = c_lang_parser.parse(source_code, None)?;
+    let function_declarator = r#"(function_declarator
+                declarator: (_) @declarator
+                parameters: (parameter_list) @parameters
+            )"#;
+    let function_declarators = format!(
+        r#"[
+            {0}
+            (pointer_declarator declarator: {0})
+            (pointer_declarator declarator: (pointer_declarator declarator: {0}))
+        ]"#,
+        function_declarator
+    );
+    // Static inline functions are defined in the header.
     let query = Query::new(
         tree_sitter_c::language(),
-        r#"(declaration
-            type: (_) @return_type
-            declarator: (function_declarator
-                declarator: (_) @declarator
-                parameters:
-                    (parameter_list
-                        (parameter_declaration
-                            type: (_)
-                            declarator: (_)
-                        )*
-                    ) @parameters
-            )
-        )"#,
+        &format!(
+            r#"[
+            (declaration type: (_) @return_type declarator: {0})
+            (function_definition type: (_) @return_type declarator: {0})
+        ]"#,
+            function_declarators
+        ),
     )
     .unwrap();
     let mut query_cursor = QueryCursor::new();
@@ -57,6 +108,8 @@ fn extract_function_declarators(
     let parameters_index = query.capture_index_for_name("parameters")?;
     let c_function = &mut CFunction::new();
     let mut c_functions = Vec::new();
+    let mut unsupported_functions = Vec::new();
+    let mut unsupported: Option<String> = None;
     let mut first_return_type = true;
     for each_match in all_matches {
         for capture in each_match.captures.iter().filter(|c| {
@@ -68,87 +121,1023 @@ fn extract_function_declarators(
             let text = &source_code[range.start_byte..range.end_byte];
             if capture.index == return_type_index {
                 if !first_return_type {
-                    c_functions.push(c_function.clone());
+                    match unsupported.take() {
+                        Some(reason) => {
+                            unsupported_functions.push((c_function.name.clone(), reason))
+                        }
+                        None => c_functions.push(c_function.clone()),
+                    }
                 }
-                c_function.return_type = text.to_string();
+                // Keep the qualifiers, as in `const char *`, so that the glue
+                // declares the function as the header does.
+                c_function.return_type =
+                    get_parameter_type_text(source_code, capture.node.parent()?)?;
                 c_function.parameters.clear();
+                c_function.variadic = false;
+                c_function.kind = if is_static(source_code, capture.node.parent()?) {
+                    FunctionKind::Inline
+                } else {
+                    FunctionKind::Extern
+                };
                 first_return_type = false;
             } else if capture.index == declarator_index {
                 c_function.name = text.to_string();
+                // `T *f(...)` nests the function declarator in pointer declarators.
+                let mut return_pointer_depth = 0;
+                let mut current_node = capture.node.parent()?;
+                while let Some(parent_node) = current_node.parent() {
+                    if parent_node.kind() != "pointer_declarator" {
+                        break;
+                    }
+                    return_pointer_depth += 1;
+                    current_node = parent_node;
+                }
+                if return_pointer_depth > 0 {
+                    c_function.return_type = format!(
+                        "{} {}",
+                        c_function.return_type,
+                        "*".repeat(return_pointer_depth)
+                    );
+                }
             } else if capture.index == parameters_index {
                 for index in 0..capture.node.child_count() {
                     let parameter_node = capture.node.child(index)?;
+                    if parameter_node.kind() == "variadic_parameter" {
+                        c_function.variadic = true;
+                    }
                     if parameter_node.kind() == "parameter_declaration" {
-                        let parameter_type_node = parameter_node.child_by_field_name("type")?;
-                        let parameter_var_node =
-                            parameter_node.child_by_field_name("declarator")?;
                         let parameter_type_text =
-                            &source_code[parameter_type_node.range().start_byte
-                                ..parameter_type_node.range().end_byte];
-                        let (pointer_depth, parameter_var_text) =
-                            CParameter::get_pointer_depth_and_var_name(
-                                source_code,
-                                parameter_var_node,
-                            );
-                        if pointer_depth > 1 {
-                            return None;
+                            &get_parameter_type_text(source_code, parameter_node)?;
+                        let parameter_var_node = parameter_node.child_by_field_name("declarator");
+                        let (pointer_depth, parameter_var_text) = match parameter_var_node {
+                            // `f(void)` declares no parameters.
+                            None if parameter_type_text == "void" => continue,
+                            None => (0, String::new()),
+                            Some(node)
+                                if node.kind() == "function_declarator"
+                                    || node.kind() == "abstract_function_declarator" =>
+                            {
+                                match extract_callback(source_code, node, parameter_type_text) {
+                                    Some(c_parameter) => c_function.parameters.push(c_parameter),
+                                    None => {
+                                        unsupported = Some(format!(
+                                            "parameter {} is a function pointer whose arguments are not supported",
+                                            c_function.parameters.len() + 1
+                                        ))
+                                    }
+                                }
+                                continue;
+                            }
+                            Some(node) => {
+                                CParameter::get_pointer_depth_and_var_name(source_code, node)
+                            }
+                        };
+                        if pointer_depth > 2 {
+                            unsupported = Some(format!(
+                                "parameter {} has more than two levels of pointers",
+                                c_function.parameters.len() + 1
+                            ));
+                            continue;
+                        }
+                        let mut array_dimensions = match parameter_var_node {
+                            Some(node) => CParameter::get_array_dimensions(source_code, node),
+                            None => Vec::new(),
+                        };
+                        // Only the first dimension may be omitted, and then the
+                        // parameter is a plain pointer.
+                        if array_dimensions.len() == 1 && array_dimensions[0].is_empty() {
+                            array_dimensions.clear();
+                        }
+                        if array_dimensions.iter().any(|d| d.is_empty()) {
+                            unsupported = Some(format!(
+                                "parameter {} is an array whose inner dimension has no size",
+                                c_function.parameters.len() + 1
+                            ));
+                            continue;
                         }
-                        c_function.parameters.push(CParameter::new(
+                        let mut c_parameter = CParameter::new(
                             &parameter_var_text,
                             parameter_type_text,
                             pointer_depth,
                             0,
-                        ));
+                        );
+                        c_parameter.set_array_dimensions(array_dimensions);
+                        if c_parameter.is_char_pointer() {
+                            c_parameter.set_string(DEFAULT_STRING_LENGTH);
+                        }
+                        c_function.parameters.push(c_parameter);
                     }
                 }
+                CParameter::name_unnamed_parameters(&mut c_function.parameters);
+                c_function.num_of_fixed_parameters = c_function.parameters.len();
             }
         }
     }
 
     if !first_return_type {
-        c_functions.push(c_function.clone());
+        match unsupported {
+            Some(reason) => unsupported_functions.push((c_function.name.clone(), reason)),
+            None => c_functions.push(c_function.clone()),
+        }
     }
 
-    Some(c_functions)
+    Some((c_functions, unsupported_functions))
 }
 
-fn c_info_source(c_functions: &Vec<CFunction>) -> String {
-    let mut s = "#include <stdio.h>\n".to_string();
-    s = "#include \"custom.h\"\n".to_string();
-    s += "int main() {\n";
-    s += "  printf(\"functions:\\n\");\n";
-    for each_function in c_functions.iter() {
-        s += &format!("  printf(\"  - func_name: {}\\n\");\n", each_function.name);
+/// Extract the enum definitions and the typedef names that refer to them.
+fn extract_enums(c_lang_parser: &mut Parser, source_code: &str) -> Option<Vec<CEnum>> {
+    let tree = c_lang_parser.parse(source_code, None)?;
+    let query = Query::new(tree_sitter_c::language(), "(enum_specifier) @enum").ok()?;
+    let mut query_cursor = QueryCursor::new();
+    let text = |node: Node| source_code[node.range().start_byte..node.range().end_byte].to_string();
+    let mut c_enums: Vec<CEnum> = Vec::new();
+    let mut aliases: Vec<(String, Vec<String>)> = Vec::new();
+    for each_match in query_cursor.matches(&query, tree.root_node(), source_code.as_bytes()) {
+        let enum_node = each_match.captures[0].node;
+        let tag = enum_node
+            .child_by_field_name("name")
+            .map(|node| format!("enum {}", text(node)));
+        // `typedef enum { ... } status_t, *status_ptr;` names the enum `status_t`.
+        let mut typedef_names = Vec::new();
+        if let Some(parent_node) = enum_node.parent() {
+            if parent_node.kind() == "type_definition" {
+                let mut cursor = parent_node.walk();
+                for declarator_node in parent_node.children_by_field_name("declarator", &mut cursor)
+                {
+                    if declarator_node.kind() == "type_identifier" {
+                        typedef_names.push(text(declarator_node));
+                    }
+                }
+            }
+        }
+        let body_node = match enum_node.child_by_field_name("body") {
+            Some(body_node) => body_node,
+            // `typedef enum status status_t;` refers to an enum that may be defined later.
+            None => {
+                if let Some(tag) = tag {
+                    aliases.push((tag, typedef_names));
+                }
+                continue;
+            }
+        };
+        let mut c_enum = CEnum::new();
+        c_enum.type_names.extend(tag);
+        c_enum.type_names.extend(typedef_names);
+        for index in 0..body_node.named_child_count() {
+            let enumerator_node = body_node.named_child(index)?;
+            if enumerator_node.kind() != "enumerator" {
+                continue;
+            }
+            c_enum.constants.push(CEnumConstant {
+                name: text(enumerator_node.child_by_field_name("name")?),
+                value: 0,
+            });
+        }
+        c_enums.push(c_enum);
+    }
+    for (tag, typedef_names) in aliases {
+        if let Some(c_enum) = c_enums.iter_mut().find(|e| e.type_names.contains(&tag)) {
+            c_enum.type_names.extend(typedef_names);
+        }
+    }
+    Some(c_enums)
+}
+
+/// The type of a field, leaving out the body of a struct or union defined in place,
+/// e.g. `union` for `union { int a; float b; } u;`.
+fn get_field_type_text(source_code: &str, field_node: Node) -> Option<String> {
+    let type_node = field_node.child_by_field_name("type")?;
+    if type_node.child_by_field_name("body").is_none() {
+        return get_parameter_type_text(source_code, field_node);
+    }
+    let keyword = match type_node.kind() {
+        "struct_specifier" => "struct",
+        "union_specifier" => "union",
+        _ => "enum",
+    };
+    Some(match type_node.child_by_field_name("name") {
+        Some(name_node) => format!(
+            "{} {}",
+            keyword,
+            &source_code[name_node.range().start_byte..name_node.range().end_byte]
+        ),
+        None => keyword.to_string(),
+    })
+}
+
+/// The name, pointer depth and array dimensions of a field declarator. A pointer to
+/// an array or a function is a pointer, whatever it points to.
+fn get_field_declarator(
+    source_code: &str,
+    declarator_node: Node,
+) -> Option<(String, u32, Vec<String>)> {
+    let mut pointer_depth = 0;
+    let mut array_dimensions = Vec::new();
+    let mut pointer_to = false;
+    let mut current_node = declarator_node;
+    loop {
+        match current_node.kind() {
+            "field_identifier" => break,
+            "pointer_declarator" => {
+                pointer_to = pointer_to || !array_dimensions.is_empty();
+                pointer_depth += 1;
+            }
+            "array_declarator" => array_dimensions.insert(
+                0,
+                match current_node.child_by_field_name("size") {
+                    Some(size_node) => source_code
+                        [size_node.range().start_byte..size_node.range().end_byte]
+                        .to_string(),
+                    None => String::new(),
+                },
+            ),
+            "function_declarator" => pointer_to = true,
+            "parenthesized_declarator" => {}
+            _ => return None,
+        }
+        current_node = current_node
+            .child_by_field_name("declarator")
+            .or_else(|| current_node.named_child(0))?;
+    }
+    if pointer_to {
+        array_dimensions.clear();
+    }
+    Some((
+        source_code[current_node.range().start_byte..current_node.range().end_byte].to_string(),
+        pointer_depth,
+        array_dimensions,
+    ))
+}
+
+/// Extract the fields of the body of a struct or union. The members of a struct or
+/// union defined in place without a tag are kept with the field that holds them.
+fn extract_fields(source_code: &str, body_node: Node) -> Option<Vec<CField>> {
+    let mut c_fields = Vec::new();
+    for index in 0..body_node.named_child_count() {
+        let field_node = body_node.named_child(index)?;
+        if field_node.kind() != "field_declaration" {
+            continue;
+        }
+        let type_text = get_field_type_text(source_code, field_node)?;
+        let type_node = field_node.child_by_field_name("type")?;
+        let members = match type_node.child_by_field_name("body") {
+            Some(members_node)
+                if type_node.child_by_field_name("name").is_none()
+                    && type_node.kind() != "enum_specifier" =>
+            {
+                let mut members = CStruct::new(type_node.kind() == "union_specifier");
+                members.fields = extract_fields(source_code, members_node)?;
+                Some(members)
+            }
+            _ => None,
+        };
+        let mut cursor = field_node.walk();
+        let bitfield = field_node
+            .named_children(&mut cursor)
+            .any(|node| node.kind() == "bitfield_clause");
+        let mut cursor = field_node.walk();
+        let declarator_nodes: Vec<Node> = field_node
+            .children_by_field_name("declarator", &mut cursor)
+            .collect();
+        // An anonymous struct or union, whose members are accessed as those of this one.
+        if declarator_nodes.is_empty() && members.is_some() {
+            let mut c_field = CField::new("", &type_text, 0);
+            c_field.members = members;
+            c_fields.push(c_field);
+            continue;
+        }
+        for declarator_node in declarator_nodes {
+            let (name, pointer_depth, array_dimensions) =
+                unwrap_some_or!(get_field_declarator(source_code, declarator_node), continue);
+            // An unnamed bitfield, as in `int : 0;`, only moves the next one.
+            if name.is_empty() {
+                continue;
+            }
+            let mut c_field = CField::new(&name, &type_text, pointer_depth);
+            c_field.array_dimensions = array_dimensions;
+            if bitfield {
+                c_field.bitfield = Some(BitField::new());
+            }
+            if pointer_depth == 0 {
+                c_field.members = members.clone();
+            }
+            c_fields.push(c_field);
+        }
+    }
+    Some(c_fields)
+}
+
+/// Extract the struct and union definitions and the typedef names that refer to them.
+/// A struct defined inside another one with a tag is declared at file scope in C,
+/// so it is extracted as well; one without a tag is described by the field that holds it.
+fn extract_structs(c_lang_parser: &mut Parser, source_code: &str) -> Option<Vec<CStruct>> {
+    let tree = c_lang_parser.parse(source_code, None)?;
+    let query = Query::new(
+        tree_sitter_c::language(),
+        "[(struct_specifier) (union_specifier)] @record",
+    )
+    .ok()?;
+    let mut query_cursor = QueryCursor::new();
+    let text = |node: Node| source_code[node.range().start_byte..node.range().end_byte].to_string();
+    let mut c_structs: Vec<CStruct> = Vec::new();
+    let mut aliases: Vec<(String, Vec<String>)> = Vec::new();
+    for each_match in query_cursor.matches(&query, tree.root_node(), source_code.as_bytes()) {
+        let record_node = each_match.captures[0].node;
+        let mut ancestor_node = record_node.parent();
+        let mut nested = false;
+        while let Some(node) = ancestor_node {
+            nested = nested
+                || matches!(node.kind(), "compound_statement" | "parameter_list")
+                || (node.kind() == "field_declaration_list"
+                    && record_node.child_by_field_name("name").is_none());
+            ancestor_node = node.parent();
+        }
+        if nested {
+            continue;
+        }
+        let is_union = record_node.kind() == "union_specifier";
+        let tag = record_node.child_by_field_name("name").map(|node| {
+            format!(
+                "{} {}",
+                if is_union { "union" } else { "struct" },
+                text(node)
+            )
+        });
+        let mut typedef_names = Vec::new();
+        if let Some(parent_node) = record_node.parent() {
+            if parent_node.kind() == "type_definition" {
+                let mut cursor = parent_node.walk();
+                for declarator_node in parent_node.children_by_field_name("declarator", &mut cursor)
+                {
+                    if declarator_node.kind() == "type_identifier" {
+                        typedef_names.push(text(declarator_node));
+                    }
+                }
+            }
+        }
+        let body_node = match record_node.child_by_field_name("body") {
+            Some(body_node) => body_node,
+            None => {
+                if let Some(tag) = tag {
+                    aliases.push((tag, typedef_names));
+                }
+                continue;
+            }
+        };
+        let mut c_struct = CStruct::new(is_union);
+        c_struct.type_names.extend(tag);
+        c_struct.type_names.extend(typedef_names);
+        // The info program cannot name a struct that has neither a tag nor a typedef.
+        if c_struct.type_names.is_empty() {
+            continue;
+        }
+        c_struct.fields = extract_fields(source_code, body_node)?;
+        c_structs.push(c_struct);
+    }
+    for (tag, typedef_names) in aliases {
+        if let Some(c_struct) = c_structs.iter_mut().find(|s| s.type_names.contains(&tag)) {
+            c_struct.type_names.extend(typedef_names);
+        }
+    }
+    Some(c_structs)
+}
+
+/// Store the fields of the enum types like `int`.
+fn mark_enum_fields(c_structs: &mut [CStruct], c_enums: &[CEnum]) {
+    for c_struct in c_structs.iter_mut() {
+        for c_field in c_struct.fields.iter_mut() {
+            c_field.is_enum = c_field.pointer_depth == 0
+                && (c_field.type_name == "enum"
+                    || c_enums
+                        .iter()
+                        .any(|e| e.type_names.contains(&c_field.type_name)));
+            if let Some(members) = &mut c_field.members {
+                mark_enum_fields(std::slice::from_mut(members), c_enums);
+            }
+        }
+    }
+}
+
+/// Extract the object-like macros whose values are constant expressions.
+/// Their values are left to the C compiler, which evaluates them in the info program.
+fn extract_constants(source_code: &str, c_enums: &[CEnum]) -> Vec<(String, ConstantKind)> {
+    let enum_constants: Vec<String> = c_enums
+        .iter()
+        .flat_map(|e| e.constants.iter().map(|c| c.name.clone()))
+        .collect();
+    cconst::constant_macros(&c_source::object_like_macros(source_code), &enum_constants)
+}
+
+/// Pass parameters of the enum types as `int`.
+fn mark_enum_types(c_functions: &mut [CFunction], c_enums: &[CEnum]) {
+    let is_enum_type = |type_name: &str| {
+        c_enums
+            .iter()
+            .any(|e| e.type_names.iter().any(|t| t == type_name))
+    };
+    for c_function in c_functions.iter_mut() {
+        if is_enum_type(&c_function.unqualified_return_type()) {
+            c_function.return_enum = true;
+        }
+        for c_parameter in c_function.parameters.iter_mut() {
+            let c_parameters = match &mut c_parameter.callback {
+                Some(callback) => &mut callback.parameters,
+                None => std::slice::from_mut(c_parameter),
+            };
+            for c_parameter in c_parameters.iter_mut() {
+                if c_parameter.pointer_depth <= 1
+                    && !c_parameter.handle
+                    && !c_parameter.is_array()
+                    && is_enum_type(&c_parameter.type_name)
+                {
+                    c_parameter.set_enum();
+                }
+            }
+        }
+    }
+}
+
+/// Drop the functions that the filter rejects and report them on stderr,
+/// together with allowlisted names that are not declared in the input.
+fn filter_functions(c_functions: &mut Vec<CFunction>, filter: &FunctionFilter) {
+    let mut filtered = Vec::new();
+    c_functions.retain(|f| {
+        let accepted = filter.accepts(&f.name);
+        if !accepted {
+            filtered.push(f.name.clone());
+        }
+        accepted
+    });
+    if !filtered.is_empty() {
+        eprintln!(
+            "Filtered out {} function(s): {}",
+            filtered.len(),
+            filtered.join(", ")
+        );
+    }
+    for name in filter.allowlist.iter() {
+        if !c_functions.iter().any(|f| f.name == *name) && !filtered.contains(name) {
+            eprintln!("Allowlisted function not found: {}", name);
+        }
+    }
+}
+
+/// Pass pointers to the given (usually opaque) types as handles.
+fn mark_handle_types(c_functions: &mut [CFunction], handle_types: &[String]) {
+    for c_function in c_functions.iter_mut() {
+        if let Some(pointee) = c_function.unqualified_return_type().strip_suffix('*') {
+            if handle_types.iter().any(|t| t == pointee.trim()) {
+                c_function.return_handle = true;
+            }
+        }
+        for c_parameter in c_function.parameters.iter_mut() {
+            if c_parameter.pointer_depth > 0 && handle_types.contains(&c_parameter.type_name) {
+                c_parameter.set_handle(false);
+            }
+        }
+    }
+}
+
+/// Give the `void *` parameters and callback arguments the types they point to.
+/// Each of `element_types` is `function.parameter=type`, or
+/// `function.callback.argument=type` for an argument of a callback.
+fn mark_element_types(
+    c_functions: &mut [CFunction],
+    element_types: &[String],
+) -> Result<(), GlueError> {
+    for element_type in element_types.iter() {
+        let invalid = || GlueError::Other(format!("Invalid element type: {}", element_type));
+        let (path, type_name) =
+            unwrap_some_or!(element_type.split_once('='), return Err(invalid()));
+        let names: Vec<&str> = path.split('.').collect();
+        if names.len() < 2 {
+            return Err(invalid());
+        }
+        let c_function = unwrap_some_or!(
+            c_functions.iter_mut().find(|f| f.name == names[0]),
+            continue
+        );
+        let mut parameters = &mut c_function.parameters;
+        for name in names[1..names.len() - 1].iter() {
+            let callback = parameters
+                .iter_mut()
+                .find(|p| p.var_name == *name)
+                .and_then(|p| p.callback.as_mut());
+            parameters = &mut unwrap_some_or!(callback, return Err(invalid())).parameters;
+        }
+        let c_parameter = unwrap_some_or!(
+            parameters
+                .iter_mut()
+                .find(|p| p.var_name == names[names.len() - 1]),
+            return Err(invalid())
+        );
+        if !c_parameter.is_void_pointer() {
+            return Err(invalid());
+        }
+        c_parameter.element_type = Some(type_name.trim().to_string());
+    }
+    Ok(())
+}
+
+/// The first `void *` parameter or callback argument without an element type,
+/// e.g. `compare.arg0` for an argument of the callback `compare`.
+fn find_void_pointer(c_function: &CFunction) -> Option<String> {
+    for c_parameter in c_function.parameters.iter() {
+        if c_parameter.is_void_pointer() && c_parameter.element_type.is_none() {
+            return Some(c_parameter.var_name.to_string());
+        }
+        if let Some(callback) = &c_parameter.callback {
+            if let Some(argument) = callback
+                .parameters
+                .iter()
+                .find(|p| p.is_void_pointer() && p.element_type.is_none())
+            {
+                return Some(format!("{}.{}", c_parameter.var_name, argument.var_name));
+            }
+        }
+    }
+    None
+}
+
+/// The type of a parameter declaration with its qualifiers, e.g. `const char` for
+/// both `const char *s` and `char const *s`. tree-sitter does not include the
+/// qualifiers in the `type` field, since they may come before or after it.
+fn get_parameter_type_text(source_code: &str, parameter_node: Node) -> Option<String> {
+    let parameter_type_node = parameter_node.child_by_field_name("type")?;
+    let mut words = Vec::new();
+    for index in 0..parameter_node.named_child_count() {
+        let child_node = parameter_node.named_child(index)?;
+        if child_node.kind() == "type_qualifier" {
+            words.push(&source_code[child_node.range().start_byte..child_node.range().end_byte]);
+        }
+    }
+    words.push(
+        &source_code[parameter_type_node.range().start_byte..parameter_type_node.range().end_byte],
+    );
+    Some(words.join(" "))
+}
+
+/// Extract a function pointer parameter such as `int (*cmp)(const void *, const void *)`.
+fn extract_callback(
+    source_code: &str,
+    function_declarator_node: Node,
+    return_type: &str,
+) -> Option<CParameter> {
+    let mut callback = CCallback::new();
+    callback.return_type = return_type.to_string();
+    let parenthesized_node = function_declarator_node.child_by_field_name("declarator")?;
+    let pointer_node = parenthesized_node.named_child(0)?;
+    if pointer_node.kind() != "pointer_declarator"
+        && pointer_node.kind() != "abstract_pointer_declarator"
+    {
+        return None;
+    }
+    let (_, var_name) = CParameter::get_pointer_depth_and_var_name(source_code, pointer_node);
+    let parameters_node = function_declarator_node.child_by_field_name("parameters")?;
+    for index in 0..parameters_node.named_child_count() {
+        let parameter_node = parameters_node.named_child(index)?;
+        if parameter_node.kind() != "parameter_declaration" {
+            continue;
+        }
+        let parameter_type_text = &get_parameter_type_text(source_code, parameter_node)?;
+        // Parameters of function pointer types are usually unnamed.
+        let (pointer_depth, parameter_var_text) =
+            match parameter_node.child_by_field_name("declarator") {
+                None if parameter_type_text == "void" => continue,
+                None => (0, String::new()),
+                Some(node) => CParameter::get_pointer_depth_and_var_name(source_code, node),
+            };
+        if pointer_depth > 1 {
+            return None;
+        }
+        callback.parameters.push(CParameter::new(
+            &parameter_var_text,
+            parameter_type_text,
+            pointer_depth,
+            0,
+        ));
+    }
+    CParameter::name_unnamed_parameters(&mut callback.parameters);
+    Some(CParameter::new_callback(&var_name, callback))
+}
+
+fn c_info_parameters_source(parameters: &[CParameter], indent: &str) -> String {
+    let mut s = "".to_string();
+    if parameters.is_empty() {
+        s += &format!("  printf(\"{}parameters: []\\n\");\n", indent);
+        return s;
+    }
+    s += &format!("  printf(\"{}parameters:\\n\");\n", indent);
+    for each_parameter in parameters.iter() {
         s += &format!(
-            "  printf(\"    return_type: {}\\n\");\n",
-            each_function.return_type
+            "  printf(\"{}  - var_name: {}\\n\");\n",
+            indent, each_parameter.var_name
+        );
+        if let Some(callback) = &each_parameter.callback {
+            s += &format!("  printf(\"{}    callback:\\n\");\n", indent);
+            s += &format!("  printf(\"{}      program: null\\n\");\n", indent);
+            s += &format!(
+                "  printf(\"{}      return_type: {}\\n\");\n",
+                indent, callback.return_type
+            );
+            s += &c_info_parameters_source(&callback.parameters, &format!("{}      ", indent));
+            continue;
+        }
+        s += &format!(
+            "  printf(\"{}    type_name: {}\\n\");\n",
+            indent, each_parameter.type_name
+        );
+        s += &format!(
+            "  printf(\"{}    pointer_depth: {}\\n\");\n",
+            indent, each_parameter.pointer_depth
         );
-        s += "  printf(\"    parameters:\\n\");\n";
-        for each_parameter in each_function.parameters.iter() {
+        if each_parameter.is_const {
+            s += &format!("  printf(\"{}    const: true\\n\");\n", indent);
+        }
+        if each_parameter.is_volatile {
+            s += &format!("  printf(\"{}    volatile: true\\n\");\n", indent);
+        }
+        if each_parameter.pointer_depth > 0 {
+            s += &format!(
+                "  printf(\"{}    direction: {}\\n\");\n",
+                indent,
+                each_parameter.direction.name()
+            );
+        }
+        if let PossibleJavaType::CString = each_parameter.java_type {
             s += &format!(
-                "  printf(\"      - var_name: {}\\n\");\n",
-                each_parameter.var_name
+                "  printf(\"{}    string_length: {}\\n\");\n",
+                indent, each_parameter.string_length
             );
+        }
+        let mut type_name = each_parameter.type_name.to_string();
+        if each_parameter.handle {
+            s += &format!("  printf(\"{}    handle: true\\n\");\n", indent);
+            type_name = "void*".to_string();
+        } else if let Some(element_type) = &each_parameter.element_type {
             s += &format!(
-                "  printf(\"        type_name: {}\\n\");\n",
-                each_parameter.type_name
+                "  printf(\"{}    element_type: {}\\n\");\n",
+                indent, element_type
             );
+            type_name = element_type.to_string();
+        } else if each_parameter.is_array() {
+            // The dimensions may be macros, so the C compiler evaluates them.
+            let formats: Vec<&str> = each_parameter
+                .array_dimensions
+                .iter()
+                .map(|_| "%lu")
+                .collect();
+            let values: Vec<String> = each_parameter
+                .array_dimensions
+                .iter()
+                .map(|d| format!("(unsigned long)({})", d))
+                .collect();
             s += &format!(
-                "  printf(\"        pointer_depth: {}\\n\");\n",
-                each_parameter.pointer_depth
+                "  printf(\"{}    array_dimensions: [{}]\\n\", {});\n",
+                indent,
+                formats.join(", "),
+                values.join(", ")
             );
-            let mut type_name = each_parameter.type_name.to_string();
-            if each_parameter.is_primitive_type() {
-                for _ in 0..each_parameter.pointer_depth {
-                    type_name += "*";
-                }
+            type_name = each_parameter.array_type_name();
+        } else if each_parameter.is_primitive_type() {
+            for _ in 0..each_parameter.pointer_depth {
+                type_name += "*";
+            }
+        } else if each_parameter.pointer_depth > 1 {
+            for _ in 1..each_parameter.pointer_depth {
+                type_name += "*";
             }
+        }
+        s += &format!(
+            "  printf(\"{}    type_size: %lu\\n\", sizeof({}));\n",
+            indent, type_name
+        );
+    }
+    s
+}
+
+/// Print a floating-point constant so that YAML reads it back as a real,
+/// e.g. `1.0` rather than `1`.
+static C_INFO_PRINT_REAL: &str = r#"static void oc4j_glue_print_real(double value) {
+  char buffer[64];
+  if (isnan(value)) {
+    printf("    value: .nan\n");
+  } else if (isinf(value)) {
+    printf("    value: %s.inf\n", value < 0 ? "-" : "");
+  } else {
+    snprintf(buffer, sizeof(buffer), "%.17g", value);
+    printf("    value: %s%s\n", buffer, strpbrk(buffer, ".e") == NULL ? ".0" : "");
+  }
+}
+"#;
+
+/// Print a string constant as a double-quoted YAML scalar.
+static C_INFO_PRINT_STRING: &str = r#"static void oc4j_glue_print_string(const char *value) {
+  printf("    value: \"");
+  for (; *value != '\0'; value++) {
+    unsigned char c = (unsigned char)*value;
+    if (c == '"' || c == '\\') {
+      printf("\\%c", c);
+    } else if (c < 0x20 || c == 0x7f) {
+      printf("\\x%02x", c);
+    } else {
+      putchar(c);
+    }
+  }
+  printf("\"\n");
+}
+"#;
+
+/// Print where a bitfield is, given the bytes of a zeroed struct in which only the
+/// bitfield is set to all ones. The unit is the smallest integer that covers the bits,
+/// starting at their first byte so that it overlaps the other fields as little as possible.
+/// Its offset is from `base`, the offset of the member that holds the bitfield.
+static C_INFO_PRINT_BITFIELD: &str = r#"static void oc4j_glue_print_bitfield(const unsigned char *bytes, size_t size, size_t base, int indent, int is_signed) {
+  size_t first = 0, last = 0, offset = 0, unit, i;
+  unsigned long long value = 0;
+  unsigned int bit_offset = 0, bit_width = 0;
+  while (first < size && bytes[first] == 0) {
+    first++;
+  }
+  for (i = first; i < size; i++) {
+    if (bytes[i] != 0) {
+      last = i;
+    }
+  }
+  for (unit = 1; unit < 8 && last >= first + unit; unit *= 2) {
+  }
+  offset = first + unit <= size ? first : size - unit;
+  if (unit == 1) {
+    uint8_t unit_value;
+    memcpy(&unit_value, bytes + offset, sizeof(unit_value));
+    value = unit_value;
+  } else if (unit == 2) {
+    uint16_t unit_value;
+    memcpy(&unit_value, bytes + offset, sizeof(unit_value));
+    value = unit_value;
+  } else if (unit == 4) {
+    uint32_t unit_value;
+    memcpy(&unit_value, bytes + offset, sizeof(unit_value));
+    value = unit_value;
+  } else {
+    uint64_t unit_value;
+    memcpy(&unit_value, bytes + offset, sizeof(unit_value));
+    value = unit_value;
+  }
+  while (bit_offset < 64 && !((value >> bit_offset) & 1)) {
+    bit_offset++;
+  }
+  while (bit_offset + bit_width < 64 && ((value >> (bit_offset + bit_width)) & 1)) {
+    bit_width++;
+  }
+  printf("%*soffset: %lu\n", indent, "", (unsigned long)(offset - base));
+  printf("%*ssize: %lu\n", indent, "", (unsigned long)unit);
+  printf("%*sbit_offset: %u\n", indent, "", bit_offset);
+  printf("%*sbit_width: %u\n", indent, "", bit_width);
+  if (is_signed) {
+    printf("%*ssigned: true\n", indent, "");
+  }
+}
+"#;
+
+/// The part of the info program that prints the fields of `type_name` whose member
+/// designators start with `prefix`, e.g. `u.` for the members of `u`. Their offsets are
+/// from `base`, the offset of the member that holds them, and each is printed at `indent`.
+/// An anonymous member has no offset or size of its own, so only its members are printed.
+fn c_info_fields_source(
+    type_name: &str,
+    c_fields: &[CField],
+    prefix: &str,
+    base: Option<&str>,
+    indent: usize,
+) -> String {
+    let mut s = "".to_string();
+    let pad = " ".repeat(indent);
+    let base_offset = match base {
+        Some(base) => format!(" - {}", base),
+        None => String::new(),
+    };
+    for c_field in c_fields.iter() {
+        let designator = format!("{}{}", prefix, c_field.name);
+        if c_field.is_anonymous() {
+            s += &format!("  printf(\"{}- name: \\\"\\\"\\n\");\n", pad);
+        } else {
+            s += &format!("  printf(\"{}- name: {}\\n\");\n", pad, c_field.name);
+        }
+        s += &format!(
+            "  printf(\"{}  type_name: {}\\n\");\n",
+            pad, c_field.type_name
+        );
+        s += &format!(
+            "  printf(\"{}  pointer_depth: {}\\n\");\n",
+            pad, c_field.pointer_depth
+        );
+        if c_field.bitfield.is_some() {
+            // offsetof and sizeof do not apply to bitfields.
+            s += "  {\n";
+            s += &format!("    {} oc4j_glue_record;\n", type_name);
+            s += "    long long oc4j_glue_ones = -1;\n";
+            s += "    memset(&oc4j_glue_record, 0, sizeof(oc4j_glue_record));\n";
+            s += &format!("    oc4j_glue_record.{} = oc4j_glue_ones;\n", designator);
+            // A signed bitfield reads back negative.
+            s += &format!("    oc4j_glue_ones = oc4j_glue_record.{};\n", designator);
+            s += &format!(
+                "    oc4j_glue_print_bitfield((const unsigned char *)&oc4j_glue_record, sizeof(oc4j_glue_record), {}, {}, oc4j_glue_ones < 0);\n",
+                base.unwrap_or("0"),
+                indent + 2
+            );
+            s += "  }\n";
+            continue;
+        }
+        if c_field.is_anonymous() {
+            if let Some(members) = &c_field.members {
+                s += &c_info_members_source(type_name, members, prefix, base, indent);
+            }
+            continue;
+        }
+        let member = format!("(({} *)0)->{}", type_name, designator);
+        if c_field.is_array() {
+            // The number of elements of each dimension, whatever macros the header uses.
+            let dimensions: Vec<String> = (0..c_field.array_dimensions.len())
+                .map(|rank| {
+                    format!(
+                        "(unsigned long)(sizeof({0}{1}) / sizeof({0}{1}[0]))",
+                        member,
+                        "[0]".repeat(rank)
+                    )
+                })
+                .collect();
+            s += &format!(
+                "  printf(\"{}  array_dimensions: [{}]\\n\", {});\n",
+                pad,
+                vec!["%lu"; dimensions.len()].join(", "),
+                dimensions.join(", ")
+            );
+        }
+        s += &format!(
+            "  printf(\"{}  offset: %lu\\n\", (unsigned long)(offsetof({}, {}){}));\n",
+            pad, type_name, designator, base_offset
+        );
+        s += &format!(
+            "  printf(\"{}  size: %lu\\n\", (unsigned long)sizeof({}));\n",
+            pad, member
+        );
+        if let Some(members) = &c_field.members {
+            // The members of the first element of an array.
+            let element = format!(
+                "{}{}",
+                designator,
+                "[0]".repeat(c_field.array_dimensions.len())
+            );
+            s += &c_info_members_source(
+                type_name,
+                members,
+                &format!("{}.", element),
+                Some(&format!("offsetof({}, {})", type_name, element)),
+                indent,
+            );
+        }
+    }
+    s
+}
+
+/// The part of the info program that prints the members of a struct or union defined
+/// in place, below the field at `indent` that holds them.
+fn c_info_members_source(
+    type_name: &str,
+    members: &CStruct,
+    prefix: &str,
+    base: Option<&str>,
+    indent: usize,
+) -> String {
+    let mut s = "".to_string();
+    let pad = " ".repeat(indent);
+    if members.is_union {
+        s += &format!("  printf(\"{}  union: true\\n\");\n", pad);
+    }
+    if members.fields.is_empty() {
+        s += &format!("  printf(\"{}  fields: []\\n\");\n", pad);
+        return s;
+    }
+    s += &format!("  printf(\"{}  fields:\\n\");\n", pad);
+    s + &c_info_fields_source(type_name, &members.fields, prefix, base, indent + 4)
+}
+
+/// The part of the info program that prints the layout of each struct and union.
+fn c_info_structs_source(c_structs: &[CStruct]) -> String {
+    let mut s = "".to_string();
+    if !c_structs.is_empty() {
+        s += "  printf(\"structs:\\n\");\n";
+    }
+    for c_struct in c_structs.iter() {
+        let type_name = c_struct.c_type_name();
+        s += &format!(
+            "  printf(\"  - type_names: [{}]\\n\");\n",
+            c_struct.type_names.join(", ")
+        );
+        if c_struct.is_union {
+            s += "  printf(\"    union: true\\n\");\n";
+        }
+        s += &format!(
+            "  printf(\"    size: %lu\\n\", (unsigned long)sizeof({}));\n",
+            type_name
+        );
+        if c_struct.fields.is_empty() {
+            s += "  printf(\"    fields: []\\n\");\n";
+            continue;
+        }
+        s += "  printf(\"    fields:\\n\");\n";
+        s += &c_info_fields_source(type_name, &c_struct.fields, "", None, 6);
+    }
+    s
+}
+
+fn c_info_source(
+    c_functions: &[CFunction],
+    c_enums: &[CEnum],
+    constants: &[(String, ConstantKind)],
+    c_structs: &[CStruct],
+) -> String {
+    let mut s = "#include <stdio.h>\n".to_string();
+    let has_bitfields = c_structs.iter().any(|s| s.has_bitfields());
+    if !c_structs.is_empty() {
+        s += "#include <stddef.h>\n";
+    }
+    if has_bitfields {
+        s += "#include <stdint.h>\n";
+    }
+    let has_kind = |kind: ConstantKind| constants.iter().any(|(_, k)| *k == kind);
+    if has_kind(ConstantKind::Real) {
+        s += "#include <math.h>\n";
+    }
+    if has_kind(ConstantKind::Real) || has_kind(ConstantKind::String) || has_bitfields {
+        s += "#include <string.h>\n";
+    }
+    s += "#include \"custom.h\"\n";
+    if has_kind(ConstantKind::Real) {
+        s += C_INFO_PRINT_REAL;
+    }
+    if has_kind(ConstantKind::String) {
+        s += C_INFO_PRINT_STRING;
+    }
+    if has_bitfields {
+        s += C_INFO_PRINT_BITFIELD;
+    }
+    s += "int main() {\n";
+    s += "  printf(\"functions:\\n\");\n";
+    for each_function in c_functions.iter() {
+        s += &format!("  printf(\"  - func_name: {}\\n\");\n", each_function.name);
+        s += &format!(
+            "  printf(\"    return_type: {}\\n\");\n",
+            each_function.return_type
+        );
+        if each_function.return_handle {
+            s += "  printf(\"    return_handle: true\\n\");\n";
+        }
+        if each_function.kind == FunctionKind::Inline {
+            s += "  printf(\"    inline: true\\n\");\n";
+        }
+        s += &c_info_parameters_source(&each_function.parameters, "    ");
+        if each_function.variadic {
+            s += "  printf(\"    variadic: true\\n\");\n";
+            s += "  printf(\"    instances: []\\n\");\n";
+        }
+    }
+    if !c_enums.is_empty() {
+        s += "  printf(\"enums:\\n\");\n";
+    }
+    for c_enum in c_enums.iter() {
+        s += &format!(
+            "  printf(\"  - type_names: [{}]\\n\");\n",
+            c_enum.type_names.join(", ")
+        );
+        s += "  printf(\"    constants:\\n\");\n";
+        for constant in c_enum.constants.iter() {
+            s += &format!("  printf(\"      - name: {}\\n\");\n", constant.name);
             s += &format!(
-                "  printf(\"        type_size: %lu\\n\", sizeof({}));\n",
-                type_name
+                "  printf(\"        value: %lld\\n\", (long long)({}));\n",
+                constant.name
             );
         }
     }
+    if !constants.is_empty() {
+        s += "  printf(\"constants:\\n\");\n";
+    }
+    for (name, kind) in constants.iter() {
+        // The macro may only be defined under some configurations.
+        s += &format!("#ifdef {}\n", name);
+        s += &format!("  printf(\"  - name: {}\\n\");\n", name);
+        s += &match kind {
+            ConstantKind::Integer => format!(
+                "  if (({0}) < 0) {{\n    printf(\"    value: %lld\\n\", (long long)({0}));\n  }} else {{\n    printf(\"    value: %llu\\n\", (unsigned long long)({0}));\n  }}\n",
+                name
+            ),
+            ConstantKind::Real => format!("  oc4j_glue_print_real((double)({}));\n", name),
+            ConstantKind::String => format!("  oc4j_glue_print_string({});\n", name),
+        };
+        s += "#endif\n";
+    }
+    s += &c_info_structs_source(c_structs);
     s += "  return 0;\n";
     s += "}\n";
     s
@@ -159,6 +1148,27 @@ enum RunningMode {
     ParseC,
     GenerateJava,
     GenerateC,
+    GenerateCToCobol,
+    GenerateCobol,
+    Package,
+    ScanCobol,
+    Check,
+}
+
+/// What the generated C does when a byte array passed from Java is shorter than
+/// the C type it is copied into.
+#[derive(Clone, Copy, Debug)]
+enum BoundsPolicy {
+    ZeroFill,
+    Exception,
+}
+
+/// How the generated Java classes call the C functions.
+#[derive(Clone, Copy, Debug)]
+enum JavaBackend {
+    Jni,
+    Ffm,
+    Jna,
 }
 
 #[derive(Debug, Clone)]
@@ -198,57 +1208,561 @@ impl fmt::Display for GlueError {
 
 impl error::Error for GlueError {}
 
-/// Convert a yaml object to a vector of CFunctions
-fn yml_to_c_function(yml: &Yaml) -> Option<Vec<CFunction>> {
-    let mut c_functions = Vec::new();
-    let root_hash = yml.as_hash()?.get(&Yaml::String("functions".to_string()))?;
-    for yml_function in root_hash.as_vec()?.iter() {
-        let mut c_function = CFunction::new();
-        let hash1 = yml_function.as_hash()?;
-        c_function.name = hash1
-            .get(&Yaml::String("func_name".to_string()))?
-            .as_str()?
-            .to_string();
-        c_function.return_type = hash1
-            .get(&Yaml::String("return_type".to_string()))?
-            .as_str()?
-            .to_string();
-        let yml_parameter_types = hash1
-            .get(&Yaml::String("parameters".to_string()))?
-            .as_vec()?;
-        for yml_parameter in yml_parameter_types.iter() {
-            let hash2 = yml_parameter.as_hash()?;
-            let var_name = hash2
-                .get(&Yaml::String("var_name".to_string()))?
-                .as_str()?
-                .to_string();
-            let type_name = hash2
-                .get(&Yaml::String("type_name".to_string()))?
+/// Convert a yaml object to a CParameter
+fn yml_to_c_parameter(yml_parameter: &Yaml) -> Option<CParameter> {
+    let hash2 = yml_parameter.as_hash()?;
+    let var_name = hash2
+        .get(&Yaml::String("var_name".to_string()))?
+        .as_str()?
+        .to_string();
+    if let Some(yml_callback) = hash2.get(&Yaml::String("callback".to_string())) {
+        return Some(CParameter::new_callback(
+            &var_name,
+            yml_to_c_callback(yml_callback)?,
+        ));
+    }
+    let type_name = hash2
+        .get(&Yaml::String("type_name".to_string()))?
+        .as_str()?
+        .to_string();
+    let pointer_depth = hash2
+        .get(&Yaml::String("pointer_depth".to_string()))?
+        .as_i64()?
+        .try_into()
+        .ok()?;
+    let type_size = hash2
+        .get(&Yaml::String("type_size".to_string()))?
+        .as_i64()?
+        .try_into()
+        .ok()?;
+    let mut c_parameter = CParameter::new(&var_name, &type_name, pointer_depth, type_size);
+    if let Some(yml_const) = hash2.get(&Yaml::String("const".to_string())) {
+        c_parameter.is_const = yml_const.as_bool()?;
+    }
+    if let Some(yml_volatile) = hash2.get(&Yaml::String("volatile".to_string())) {
+        c_parameter.is_volatile = yml_volatile.as_bool()?;
+    }
+    if let Some(yml_array_dimensions) = hash2.get(&Yaml::String("array_dimensions".to_string())) {
+        let mut array_dimensions = Vec::new();
+        for yml_dimension in yml_array_dimensions.as_vec()?.iter() {
+            array_dimensions.push(yml_dimension.as_i64()?.to_string());
+        }
+        if array_dimensions.is_empty() || c_parameter.pointer_depth == 0 {
+            return None;
+        }
+        c_parameter.set_array_dimensions(array_dimensions);
+    }
+    let handle = match hash2.get(&Yaml::String("handle".to_string())) {
+        Some(yml_handle) => yml_handle.as_bool()?,
+        None => false,
+    };
+    let release_handle = match hash2.get(&Yaml::String("release_handle".to_string())) {
+        Some(yml_release_handle) => yml_release_handle.as_bool()?,
+        None => false,
+    };
+    // `const T*` parameters are only read unless the schema says otherwise.
+    c_parameter.direction = match hash2.get(&Yaml::String("direction".to_string())) {
+        Some(yml_direction) if c_parameter.pointer_depth > 0 => {
+            Direction::from_name(yml_direction.as_str()?)?
+        }
+        Some(_) => return None,
+        None if c_parameter.is_const && c_parameter.pointer_depth == 1 => Direction::In,
+        None => Direction::InOut,
+    };
+    if handle {
+        if c_parameter.pointer_depth == 0 || (release_handle && pointer_depth != 1) {
+            return None;
+        }
+        c_parameter.set_handle(release_handle);
+    } else if release_handle || c_parameter.pointer_depth > 1 {
+        return None;
+    }
+    match hash2.get(&Yaml::String("element_type".to_string())) {
+        Some(yml_element_type) if c_parameter.is_void_pointer() => {
+            c_parameter.element_type = Some(yml_element_type.as_str()?.to_string());
+        }
+        Some(_) => return None,
+        None if c_parameter.is_void_pointer() => {
+            eprintln!(
+                "{} is a void pointer; give the type it points to as element_type",
+                var_name
+            );
+            return None;
+        }
+        None => {}
+    }
+    Some(c_parameter)
+}
+
+/// Convert a yaml object to a parameter of a C function, which takes a `char *` as a string.
+fn yml_to_c_function_parameter(yml_parameter: &Yaml) -> Option<CParameter> {
+    let mut c_parameter = yml_to_c_parameter(yml_parameter)?;
+    let yml_string_length = yml_parameter
+        .as_hash()?
+        .get(&Yaml::String("string_length".to_string()));
+    if c_parameter.is_char_pointer() {
+        let string_length = match yml_string_length {
+            Some(yml_string_length) => yml_string_length.as_i64()?.try_into().ok()?,
+            None => DEFAULT_STRING_LENGTH,
+        };
+        c_parameter.set_string(string_length);
+    } else if yml_string_length.is_some() {
+        return None;
+    }
+    Some(c_parameter)
+}
+
+/// Convert a yaml object to a CCallback
+fn yml_to_c_callback(yml_callback: &Yaml) -> Option<CCallback> {
+    let mut callback = CCallback::new();
+    let hash = yml_callback.as_hash()?;
+    callback.program = hash
+        .get(&Yaml::String("program".to_string()))?
+        .as_str()?
+        .to_string();
+    callback.return_type = hash
+        .get(&Yaml::String("return_type".to_string()))?
+        .as_str()?
+        .to_string();
+    if callback.return_type != "void"
+        && !CParameter::new("", &callback.return_type, 0, 0).is_primitive_type()
+    {
+        return None;
+    }
+    let yml_parameters = hash
+        .get(&Yaml::String("parameters".to_string()))?
+        .as_vec()?;
+    for yml_parameter in yml_parameters.iter() {
+        let c_parameter = yml_to_c_parameter(yml_parameter)?;
+        if c_parameter.handle || c_parameter.callback.is_some() {
+            return None;
+        }
+        callback.parameters.push(c_parameter);
+    }
+    Some(callback)
+}
+
+/// Convert a yaml object to a CEnum
+fn yml_to_c_enum(yml_enum: &Yaml) -> Option<CEnum> {
+    let mut c_enum = CEnum::new();
+    let hash = yml_enum.as_hash()?;
+    for yml_type_name in hash
+        .get(&Yaml::String("type_names".to_string()))?
+        .as_vec()?
+        .iter()
+    {
+        c_enum.type_names.push(yml_type_name.as_str()?.to_string());
+    }
+    for yml_constant in hash
+        .get(&Yaml::String("constants".to_string()))?
+        .as_vec()?
+        .iter()
+    {
+        let hash2 = yml_constant.as_hash()?;
+        c_enum.constants.push(CEnumConstant {
+            name: hash2
+                .get(&Yaml::String("name".to_string()))?
                 .as_str()?
-                .to_string();
-            let pointer_depth = hash2
-                .get(&Yaml::String("pointer_depth".to_string()))?
-                .as_i64()?
-                .try_into()
-                .ok()?;
-            let type_size = hash2
-                .get(&Yaml::String("type_size".to_string()))?
-                .as_i64()?
-                .try_into()
-                .ok()?;
-            c_function.parameters.push(CParameter::new(
-                &var_name,
-                &type_name,
-                pointer_depth,
-                type_size,
-            ));
-        }
-        c_functions.push(c_function);
+                .to_string(),
+            value: hash2.get(&Yaml::String("value".to_string()))?.as_i64()?,
+        });
+    }
+    Some(c_enum)
+}
+
+/// Convert a yaml object to a CConstant
+fn yml_to_c_constant(yml_constant: &Yaml) -> Option<CConstant> {
+    let hash = yml_constant.as_hash()?;
+    let value = match hash.get(&Yaml::String("value".to_string()))? {
+        Yaml::Integer(value) => ConstantValue::Integer(*value as i128),
+        // yaml-rust keeps integers beyond i64 as reals.
+        Yaml::Real(value) => match value.parse::<i128>() {
+            Ok(value) => ConstantValue::Integer(value),
+            Err(_) => ConstantValue::Real(Yaml::Real(value.clone()).as_f64()?),
+        },
+        Yaml::String(value) => ConstantValue::String(value.clone()),
+        _ => return None,
+    };
+    Some(CConstant {
+        name: hash
+            .get(&Yaml::String("name".to_string()))?
+            .as_str()?
+            .to_string(),
+        value,
+    })
+}
+
+/// Convert a yaml object to a CField
+fn yml_to_c_field(yml_field: &Yaml) -> Option<CField> {
+    let hash = yml_field.as_hash()?;
+    let get = |key: &str| hash.get(&Yaml::String(key.to_string()));
+    let mut c_field = CField::new(
+        get("name")?.as_str()?,
+        get("type_name")?.as_str()?,
+        get("pointer_depth")?.as_i64()?.try_into().ok()?,
+    );
+    if let Some(yml_fields) = get("fields") {
+        let is_union = match get("union") {
+            Some(yml_union) => yml_union.as_bool()?,
+            None => false,
+        };
+        let mut members = CStruct::new(is_union);
+        for yml_member in yml_fields.as_vec()?.iter() {
+            members.fields.push(yml_to_c_field(yml_member)?);
+        }
+        c_field.members = Some(members);
+    }
+    if c_field.is_anonymous() {
+        // An anonymous member starts at its first member, and its members' offsets
+        // are printed from the start of the member that holds it.
+        let members = c_field.members.as_mut()?;
+        c_field.offset = members.fields.iter().map(|f| f.offset).min()?;
+        c_field.size = members.fields.iter().map(|f| f.offset + f.size).max()? - c_field.offset;
+        for member in members.fields.iter_mut() {
+            member.offset -= c_field.offset;
+        }
+        members.size = c_field.size;
+        return Some(c_field);
+    }
+    c_field.offset = get("offset")?.as_i64()?.try_into().ok()?;
+    c_field.size = get("size")?.as_i64()?.try_into().ok()?;
+    if let Some(yml_array_dimensions) = get("array_dimensions") {
+        for yml_dimension in yml_array_dimensions.as_vec()?.iter() {
+            c_field
+                .array_dimensions
+                .push(yml_dimension.as_i64()?.to_string());
+        }
+    }
+    if let Some(yml_bit_width) = get("bit_width") {
+        c_field.bitfield = Some(BitField {
+            bit_offset: get("bit_offset")?.as_i64()?.try_into().ok()?,
+            bit_width: yml_bit_width.as_i64()?.try_into().ok()?,
+            signed: match get("signed") {
+                Some(yml_signed) => yml_signed.as_bool()?,
+                None => false,
+            },
+        });
+    }
+    if let Some(members) = &mut c_field.members {
+        let num_of_elements: u32 = c_field
+            .array_dimensions
+            .iter()
+            .map(|d| d.parse::<u32>().unwrap_or(0))
+            .product();
+        members.size = c_field.size.checked_div(num_of_elements).unwrap_or(0);
+    }
+    Some(c_field)
+}
+
+/// Convert a yaml object to a CStruct
+fn yml_to_c_struct(yml_struct: &Yaml) -> Option<CStruct> {
+    let hash = yml_struct.as_hash()?;
+    let is_union = match hash.get(&Yaml::String("union".to_string())) {
+        Some(yml_union) => yml_union.as_bool()?,
+        None => false,
+    };
+    let mut c_struct = CStruct::new(is_union);
+    for yml_type_name in hash
+        .get(&Yaml::String("type_names".to_string()))?
+        .as_vec()?
+        .iter()
+    {
+        c_struct
+            .type_names
+            .push(yml_type_name.as_str()?.to_string());
+    }
+    if c_struct.type_names.is_empty() {
+        return None;
+    }
+    c_struct.size = hash
+        .get(&Yaml::String("size".to_string()))?
+        .as_i64()?
+        .try_into()
+        .ok()?;
+    for yml_field in hash
+        .get(&Yaml::String("fields".to_string()))?
+        .as_vec()?
+        .iter()
+    {
+        c_struct.fields.push(yml_to_c_field(yml_field)?);
+    }
+    Some(c_struct)
+}
+
+/// Convert a yaml object to a CFunction, or to the fixed-arity instances of a variadic one
+fn yml_to_c_functions(yml_function: &Yaml) -> Option<Vec<CFunction>> {
+    let mut c_function = CFunction::new();
+    let hash1 = yml_function.as_hash()?;
+    c_function.name = hash1
+        .get(&Yaml::String("func_name".to_string()))?
+        .as_str()?
+        .to_string();
+    c_function.return_type = hash1
+        .get(&Yaml::String("return_type".to_string()))?
+        .as_str()?
+        .to_string();
+    let yml_parameter_types = hash1
+        .get(&Yaml::String("parameters".to_string()))?
+        .as_vec()?;
+    for yml_parameter in yml_parameter_types.iter() {
+        c_function
+            .parameters
+            .push(yml_to_c_function_parameter(yml_parameter)?);
+    }
+    if let Some(yml_return_handle) = hash1.get(&Yaml::String("return_handle".to_string())) {
+        c_function.return_handle = yml_return_handle.as_bool()?;
+        if c_function.return_handle && !c_function.returns_pointer() {
+            return None;
+        }
+    }
+    if let Some(yml_error_values) = hash1.get(&Yaml::String("error_values".to_string())) {
+        for yml_error_value in yml_error_values.as_vec()?.iter() {
+            let error_value = match yml_error_value {
+                Yaml::String(s) if s == "negative" => ErrorValue::Negative,
+                Yaml::String(s) if s == "null" => ErrorValue::Null,
+                Yaml::Null => ErrorValue::Null,
+                Yaml::Integer(i) => ErrorValue::Value(*i),
+                _ => return None,
+            };
+            let valid = match error_value {
+                ErrorValue::Null => c_function.returns_pointer(),
+                _ => !c_function.returns_pointer() && !c_function.returns_void(),
+            };
+            if !valid {
+                return None;
+            }
+            c_function.error_values.push(error_value);
+        }
+    }
+    if let Some(yml_check_errno) = hash1.get(&Yaml::String("check_errno".to_string())) {
+        c_function.check_errno = yml_check_errno.as_bool()?;
+    }
+    // Static inline functions and function-like macros are called through a wrapper.
+    if let Some(yml_inline) = hash1.get(&Yaml::String("inline".to_string())) {
+        if yml_inline.as_bool()? {
+            c_function.kind = FunctionKind::Inline;
+        }
+    }
+    if let Some(yml_macro) = hash1.get(&Yaml::String("macro".to_string())) {
+        if yml_macro.as_bool()? {
+            if c_function.kind == FunctionKind::Inline {
+                return None;
+            }
+            c_function.kind = FunctionKind::Macro;
+        }
+    }
+    if let Some(yml_variadic) = hash1.get(&Yaml::String("variadic".to_string())) {
+        c_function.variadic = yml_variadic.as_bool()?;
+    }
+    if !c_function.variadic {
+        return Some(vec![c_function]);
+    }
+    // A variadic function is called through its fixed-arity instances, each of
+    // which gets its own glue with the variadic arguments as extra parameters.
+    c_function.num_of_fixed_parameters = c_function.parameters.len();
+    let mut c_functions = Vec::new();
+    let yml_instances = match hash1.get(&Yaml::String("instances".to_string())) {
+        Some(yml_instances) => yml_instances.as_vec()?.clone(),
+        None => Vec::new(),
+    };
+    if yml_instances.is_empty() {
+        eprintln!(
+            "Skipping the variadic function {}, which has no instances",
+            c_function.name
+        );
+    }
+    for yml_instance in yml_instances.iter() {
+        let hash2 = yml_instance.as_hash()?;
+        let mut instance = c_function.clone();
+        instance.name = hash2
+            .get(&Yaml::String("func_name".to_string()))?
+            .as_str()?
+            .to_string();
+        instance.instance_of = Some(c_function.name.clone());
+        for yml_parameter in hash2
+            .get(&Yaml::String("parameters".to_string()))?
+            .as_vec()?
+            .iter()
+        {
+            let c_parameter = yml_to_c_function_parameter(yml_parameter)?;
+            if instance
+                .parameters
+                .iter()
+                .any(|p| p.var_name == c_parameter.var_name)
+            {
+                return None;
+            }
+            instance.parameters.push(c_parameter);
+        }
+        // A printf-style function reads its format and `%s` arguments up to a NUL,
+        // so every `char *` must be passed as a string rather than a handle or an array.
+        if let Some(p) = instance.parameters.iter().find(|p| {
+            p.type_name == "char"
+                && p.pointer_depth == 1
+                && !matches!(p.java_type, PossibleJavaType::CString)
+        }) {
+            eprintln!(
+                "{}: {} of the variadic function {} must be passed as a string",
+                instance.name, p.var_name, c_function.name
+            );
+            return None;
+        }
+        c_functions.push(instance);
     }
     Some(c_functions)
 }
 
-fn get_java_file_content(c_function: &CFunction) -> String {
+/// Convert a yaml object to a CobolProgram
+fn yml_to_cobol_program(yml_program: &Yaml) -> Option<CobolProgram> {
+    let mut cobol_program = CobolProgram::new();
+    let hash1 = yml_program.as_hash()?;
+    cobol_program.program_name = hash1
+        .get(&Yaml::String("program_name".to_string()))?
+        .as_str()?
+        .to_string();
+    cobol_program.func_name = match hash1.get(&Yaml::String("func_name".to_string())) {
+        Some(yml_func_name) => yml_func_name.as_str()?.to_string(),
+        None => cobol_program.program_name.to_string(),
+    };
+    let yml_parameter_types = hash1
+        .get(&Yaml::String("parameters".to_string()))?
+        .as_vec()?;
+    for yml_parameter in yml_parameter_types.iter() {
+        let c_parameter = yml_to_c_parameter(yml_parameter)?;
+        if c_parameter.handle || c_parameter.callback.is_some() {
+            return None;
+        }
+        cobol_program.parameters.push(c_parameter);
+    }
+    Some(cobol_program)
+}
+
+/// The class that holds the constant macros as `static final` fields.
+static JAVA_CONSTANTS_CLASS_NAME: &str = "CGlueConstants";
+
+/// The runtime classes that the generated classes extend and throw. Without
+/// `--java-package` they are compiled from the copies in this repository; with it,
+/// `generate_java` writes them into the package next to the generated classes,
+/// since a class in a named package cannot refer to one in the unnamed package.
+static JAVA_RUNTIME_CLASSES: [(&str, &str); 3] = [
+    (
+        "CobolRunnableCGlue",
+        include_str!("../CobolRunnableCGlue.java"),
+    ),
+    ("CGlueException", include_str!("../CGlueException.java")),
+    (
+        "CGlueHandleRegistry",
+        include_str!("../CGlueHandleRegistry.java"),
+    ),
+];
+
+/// A Java package name is a dot-separated list of identifiers. `$` is not accepted,
+/// so that the JNI names derived from it need no escapes other than `_1`.
+fn is_java_package_name(name: &str) -> bool {
+    name.split('.').all(|identifier| {
+        let mut chars = identifier.chars();
+        matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+/// The `package` declaration that starts every generated Java file.
+fn get_java_package_declaration(java_package: Option<&str>) -> String {
+    match java_package {
+        Some(java_package) => format!("package {};\n", java_package),
+        None => "".to_string(),
+    }
+}
+
+/// The binary name of a class in `java_package`, with `/` as `FindClass` takes it.
+fn get_jni_class_path(java_package: Option<&str>, class_name: &str) -> String {
+    match java_package {
+        Some(java_package) => format!("{}/{}", java_package.replace('.', "/"), class_name),
+        None => class_name.to_string(),
+    }
+}
+
+/// The header that `javac -h` writes for a class in `java_package`.
+fn get_jni_header_name(java_package: Option<&str>, class_name: &str) -> String {
+    get_jni_class_path(java_package, class_name).replace('/', "_") + ".h"
+}
+
+/// The symbol that the JVM looks up for a native method that is not registered.
+/// `_` is escaped as `_1` so that it cannot be read as a package separator.
+fn get_jni_symbol(java_package: Option<&str>, class_name: &str, method_name: &str) -> String {
+    format!(
+        "Java_{}_{}",
+        get_jni_class_path(java_package, class_name)
+            .replace('_', "_1")
+            .replace('/', "_"),
+        method_name.replace('_', "_1")
+    )
+}
+
+/// A Java string literal for `value`. Control characters are written as octal escapes
+/// and the other characters outside printable ASCII as unicode escapes.
+fn java_string_literal(value: &str) -> String {
+    let mut s = "\"".to_string();
+    for c in value.chars() {
+        match c {
+            '"' => s += "\\\"",
+            '\\' => s += "\\\\",
+            ' '..='~' => s.push(c),
+            // Unicode escapes are translated before the literal is read, so a line
+            // terminator must not be written as one.
+            '\0'..='\u{1f}' | '\u{7f}' => s += &format!("\\{:03o}", c as u32),
+            _ => {
+                let mut units = [0u16; 2];
+                for unit in c.encode_utf16(&mut units).iter() {
+                    s += &format!("\\u{:04x}", unit);
+                }
+            }
+        }
+    }
+    s + "\""
+}
+
+/// A class with a `static final` field for each constant macro. Integers are `int`
+/// when they fit and `long` otherwise; unsigned values beyond `long` keep their bits.
+fn get_java_constants_file_content(c_constants: &[CConstant]) -> String {
+    let mut s = "".to_string();
+    s += &format!("public final class {} {{\n", JAVA_CONSTANTS_CLASS_NAME);
+    s += &format!("  private {}() {{}}\n", JAVA_CONSTANTS_CLASS_NAME);
+    for constant in c_constants.iter() {
+        let (java_type, literal) = match &constant.value {
+            ConstantValue::Integer(value) => {
+                if i32::try_from(*value).is_ok() {
+                    ("int", value.to_string())
+                } else if i64::try_from(*value).is_ok() {
+                    ("long", format!("{}L", value))
+                } else {
+                    ("long", format!("0x{:x}L", *value as u64))
+                }
+            }
+            ConstantValue::Real(value) => (
+                "double",
+                if value.is_nan() {
+                    "Double.NaN".to_string()
+                } else if value.is_infinite() && *value > 0.0 {
+                    "Double.POSITIVE_INFINITY".to_string()
+                } else if value.is_infinite() {
+                    "Double.NEGATIVE_INFINITY".to_string()
+                } else {
+                    format!("{:?}", value)
+                },
+            ),
+            ConstantValue::String(value) => ("String", java_string_literal(value)),
+        };
+        s += &format!(
+            "  public static final {} {} = {};\n",
+            java_type, constant.name, literal
+        );
+    }
+    s += "}\n";
+    s
+}
+
+fn get_java_file_content(c_function: &CFunction, library_name: &str) -> String {
     let mut s = "".to_string();
     s += "import jp.osscons.opensourcecobol.libcobj.data.CobolDataStorage;\n";
 
@@ -257,166 +1771,1162 @@ fn get_java_file_content(c_function: &CFunction) -> String {
         c_function.name
     );
 
-    s += &format!("  public native void {}(", c_function.name);
-    let num_of_parameters = c_function.parameters.len();
-    for (i, parameter_type) in c_function.parameters.iter().enumerate() {
-        s += &format!("{} {}", parameter_type.java_type, parameter_type.var_name);
-        if i != num_of_parameters - 1 {
-            s += ", ";
-        }
-    }
-    s += ");\n";
-
+    let native_return_type = if c_function.return_handle {
+        "long"
+    } else {
+        "void"
+    };
+    // Callback parameters are not passed from Java; the C glue supplies a trampoline.
+    let native_parameters: Vec<String> = c_function
+        .parameters
+        .iter()
+        .filter(|p| p.callback.is_none())
+        .map(|p| {
+            if p.copies_back_value() {
+                format!("{}[] {}", p.java_type, p.var_name)
+            } else {
+                format!("{} {}", p.java_type, p.var_name)
+            }
+        })
+        .collect();
     s += &format!(
-        "  static {{ System.loadLibrary(\"{}\"); }}\n",
-        c_function.name
+        "  public native {} {}({});\n",
+        native_return_type,
+        c_function.name,
+        native_parameters.join(", ")
     );
 
-    s += "  @Override\n";
-    s += "  public int run(CobolDataStorage... argStorages) {\n";
+    s += &format!(
+        "  static {{ CobolRunnableCGlue.loadLibrary(\"{}\"); }}\n",
+        library_name
+    );
 
-    for (i, parameter_type) in c_function.parameters.iter().enumerate() {
-        match parameter_type.java_type {
-            PossibleJavaType::ByteArray => {
-                s += &format!(
-                    "    byte[] {} = storageToByteArray(argStorages[{}], {});\n",
-                    parameter_type.var_name, i, parameter_type.type_size
-                );
-            }
-            _ => {}
+    s += &java_run::get_run_prelude(c_function);
+    let storage_indices = c_function.storage_indices();
+    for (parameter_type, i) in c_function.parameters.iter().zip(storage_indices.iter()) {
+        if let PossibleJavaType::HandleOut = parameter_type.java_type {
+            s += &format!("    long[] {} = new long[1];\n", parameter_type.var_name);
         }
+        // The C function may write through the pointer, so the value goes in an array.
+        if parameter_type.copies_back_value() {
+            s += &format!(
+                "    {}[] {} = {{ {}(argStorages[{}]) }};\n",
+                parameter_type.java_type,
+                parameter_type.var_name,
+                java_run::storage_accessor(&parameter_type.java_type),
+                i
+            );
+        }
+    }
+    s += &java_run::get_run_try(c_function, None);
+    s += "      ";
+    if c_function.return_handle {
+        s += "long returnedHandle = ";
     }
-    s += &format!("    {}(", c_function.name);
-    for (i, parameter_type) in c_function.parameters.iter().enumerate() {
+    let mut arguments = Vec::new();
+    for (parameter_type, i) in c_function.parameters.iter().zip(storage_indices.iter()) {
+        if parameter_type.copies_back_value() {
+            arguments.push(parameter_type.var_name.to_string());
+            continue;
+        }
         match parameter_type.java_type {
-            PossibleJavaType::Byte => {
-                s += &format!("storageToByte(argStorages[{}])", i);
+            PossibleJavaType::Byte | PossibleJavaType::Short | PossibleJavaType::Int => {
+                arguments.push(format!(
+                    "{}(argStorages[{}])",
+                    java_run::storage_accessor(&parameter_type.java_type),
+                    i
+                ));
             }
-            PossibleJavaType::Short => {
-                s += &format!("storageToShort(argStorages[{}])", i);
+            PossibleJavaType::ByteArray
+            | PossibleJavaType::CString
+            | PossibleJavaType::HandleOut => {
+                arguments.push(parameter_type.var_name.to_string());
             }
-            PossibleJavaType::Int => {
-                s += &format!("storageToInt(argStorages[{}])", i);
-            }
-            PossibleJavaType::ByteArray => {
-                //s += &format!("{}", parameter_type.var_name);
-                s += parameter_type.var_name.to_string().as_str();
+            PossibleJavaType::Handle => {
+                arguments.push(format!(
+                    "CGlueHandleRegistry.resolve(storageToInt(argStorages[{}]))",
+                    i
+                ));
             }
+            PossibleJavaType::Callback => {}
         };
-        if i != num_of_parameters - 1 {
-            s += ", ";
+    }
+    s += &format!("{}({});\n", c_function.name, arguments.join(", "));
+    for (parameter_type, i) in c_function.parameters.iter().zip(storage_indices.iter()) {
+        if let PossibleJavaType::HandleOut = parameter_type.java_type {
+            s += &format!(
+                "      intToStorage(argStorages[{}], CGlueHandleRegistry.register({}[0]));\n",
+                i, parameter_type.var_name
+            );
         }
     }
-    s += ");\n";
-    for (i, parameter_type) in c_function.parameters.iter().enumerate() {
-        match parameter_type.java_type {
-            PossibleJavaType::ByteArray => {
-                s += &format!(
-                    "    bytesToStorage(argStorages[{}], {});\n",
-                    i, parameter_type.var_name
-                );
-            }
-            _ => {}
+    s += &java_run::get_handle_copy_back(c_function);
+    s += &java_run::get_returned_handle(c_function, "returnedHandle");
+    let mut copy_back = "".to_string();
+    for (parameter_type, i) in c_function.parameters.iter().zip(storage_indices.iter()) {
+        if parameter_type.copies_back_value() {
+            copy_back += &format!(
+                "    {}ToStorage(argStorages[{}], {}[0]);\n",
+                parameter_type.java_type, i, parameter_type.var_name
+            );
+        }
+    }
+    s += &java_run::get_run_epilogue(c_function, false, &copy_back);
+    s
+}
+
+static C_LOCAL_PARAM_PREFIX: &str = "oc4j_glue_";
+
+/// The prefix that the static C functions put before the runtime class names they find.
+fn get_c_package_macro(java_package: Option<&str>) -> String {
+    format!(
+        "#define OC4J_GLUE_PACKAGE \"{}\"\n",
+        get_jni_class_path(java_package, "")
+    )
+}
+
+static C_THROW_FUNCTION: &str = r#"static void oc4j_glue_throw(JNIEnv *env, const char *message, int error_number, jlong return_value)
+{
+  jclass exception_class = (*env)->FindClass(env, OC4J_GLUE_PACKAGE "CGlueException");
+  if (exception_class == NULL) {
+    return;
+  }
+  jmethodID constructor = (*env)->GetMethodID(env, exception_class, "<init>", "(Ljava/lang/String;IJ)V");
+  if (constructor == NULL) {
+    return;
+  }
+  jstring jmessage = (*env)->NewStringUTF(env, message);
+  jobject exception = (*env)->NewObject(env, exception_class, constructor, jmessage, error_number, return_value);
+  if (exception != NULL) {
+    (*env)->Throw(env, (jthrowable)exception);
+  }
+}
+"#;
+
+static C_TO_BIG_ENDIAN_FUNCTION: &str = r#"static void oc4j_glue_to_big_endian(jbyte *bytes, long long value, int size)
+{
+  for (int i = size - 1; i >= 0; i--) {
+    bytes[i] = (jbyte)(value & 0xff);
+    value >>= 8;
+  }
+}
+"#;
+
+static C_FROM_BIG_ENDIAN_FUNCTION: &str = r#"static long long oc4j_glue_from_big_endian(const jbyte *bytes, int size)
+{
+  long long value = bytes[0] < 0 ? -1 : 0;
+  for (int i = 0; i < size; i++) {
+    value = (value << 8) | (unsigned char)bytes[i];
+  }
+  return value;
+}
+"#;
+
+static C_CALLBACK_FUNCTIONS: &str = r#"static JavaVM *oc4j_glue_jvm = NULL;
+static jint oc4j_glue_call_program(JNIEnv *env, const char *program, jobjectArray arguments)
+{
+  jclass glue_class = (*env)->FindClass(env, OC4J_GLUE_PACKAGE "CobolRunnableCGlue");
+  if (glue_class == NULL) {
+    return 0;
+  }
+  jmethodID call_program = (*env)->GetStaticMethodID(env, glue_class, "callProgram", "(Ljava/lang/String;[[B)I");
+  if (call_program == NULL) {
+    return 0;
+  }
+  jstring jprogram = (*env)->NewStringUTF(env, program);
+  jint result = (*env)->CallStaticIntMethod(env, glue_class, call_program, jprogram, arguments);
+  (*env)->DeleteLocalRef(env, jprogram);
+  (*env)->DeleteLocalRef(env, glue_class);
+  return result;
+}
+"#;
+
+/// The number of bytes a COBOL program sees for a C parameter.
+fn get_c_byte_array_size(p: &CParameter) -> String {
+    if p.is_primitive_type() || p.pointer_depth == 0 {
+        format!("sizeof({})", p.type_name)
+    } else {
+        p.type_size.to_string()
+    }
+}
+
+/// The big-endian conversions that copying `parameters` to and from byte arrays uses,
+/// since an unused static function is warned about.
+fn get_c_big_endian_functions(parameters: &[&CParameter]) -> String {
+    let mut s = "".to_string();
+    if parameters.iter().any(|p| p.is_primitive_type()) {
+        s += C_TO_BIG_ENDIAN_FUNCTION;
+    }
+    if parameters
+        .iter()
+        .any(|p| p.is_primitive_type() && p.pointer_depth == 1 && p.copies_back())
+    {
+        s += C_FROM_BIG_ENDIAN_FUNCTION;
+    }
+    s
+}
+
+/// Copy the C value `var` into a new byte array `jarg_<var_name>`:
+/// integers in big-endian like COBOL binary items, structs as they are laid out in memory.
+fn get_c_to_byte_array(p: &CParameter, var: &str, indent: &str) -> String {
+    let mut s = "".to_string();
+    let size = get_c_byte_array_size(p);
+    s += &format!(
+        "{}jbyteArray jarg_{} = (*env)->NewByteArray(env, {});\n",
+        indent, p.var_name, size
+    );
+    if p.is_primitive_type() {
+        let value = if p.pointer_depth == 0 {
+            var.to_string()
+        } else {
+            format!("{} == NULL ? 0 : *{}", var, var)
+        };
+        s += &format!("{}jbyte bytes_{}[{}];\n", indent, p.var_name, size);
+        s += &format!(
+            "{}oc4j_glue_to_big_endian(bytes_{}, (long long)({}), {});\n",
+            indent, p.var_name, value, size
+        );
+        s += &format!(
+            "{}(*env)->SetByteArrayRegion(env, jarg_{}, 0, {}, bytes_{});\n",
+            indent, p.var_name, size, p.var_name
+        );
+    } else if p.pointer_depth == 0 {
+        s += &format!(
+            "{}(*env)->SetByteArrayRegion(env, jarg_{}, 0, {}, (const jbyte *)&{});\n",
+            indent, p.var_name, size, var
+        );
+    } else {
+        s += &format!("{}if ({} != NULL) {{\n", indent, var);
+        s += &format!(
+            "{}  (*env)->SetByteArrayRegion(env, jarg_{}, 0, {}, (const jbyte *){});\n",
+            indent, p.var_name, size, var
+        );
+        s += &format!("{}}}\n", indent);
+    }
+    s
+}
+
+/// Copy the byte array `jarg_<var_name>` back to what the pointer `var` points to,
+/// unless the C side only reads it.
+fn get_c_from_byte_array(p: &CParameter, var: &str, indent: &str) -> String {
+    let mut s = "".to_string();
+    if p.pointer_depth != 1 || !p.copies_back() {
+        return s;
+    }
+    s += &format!(
+        "{}if ({} != NULL && !(*env)->ExceptionCheck(env)) {{\n",
+        indent, var
+    );
+    if p.is_primitive_type() {
+        s += &format!(
+            "{}  (*env)->GetByteArrayRegion(env, jarg_{}, 0, sizeof({}), bytes_{});\n",
+            indent, p.var_name, p.type_name, p.var_name
+        );
+        s += &format!(
+            "{}  *{} = ({})oc4j_glue_from_big_endian(bytes_{}, sizeof({}));\n",
+            indent, var, p.type_name, p.var_name, p.type_name
+        );
+    } else {
+        s += &format!(
+            "{}  (*env)->GetByteArrayRegion(env, jarg_{}, 0, {}, (jbyte *){});\n",
+            indent, p.var_name, p.type_size, var
+        );
+    }
+    s += &format!("{}}}\n", indent);
+    s
+}
+
+/// A C function with the signature of `callback` that runs its COBOL program.
+fn get_c_trampoline(c_function: &CFunction, param: &CParameter, callback: &CCallback) -> String {
+    let mut s = "".to_string();
+    let c_parameters: Vec<String> = callback
+        .parameters
+        .iter()
+        .map(|p| format!("{} {}", p.pointer_type_name(), p.var_name))
+        .collect();
+    s += &format!(
+        "static {} {}({})\n{{\n",
+        callback.return_type,
+        CCallback::trampoline_name(&c_function.name, &param.var_name),
+        if c_parameters.is_empty() {
+            "void".to_string()
+        } else {
+            c_parameters.join(", ")
         }
+    );
+    let return_statement = if callback.return_type == "void" {
+        "return;".to_string()
+    } else {
+        format!("return ({})result;", callback.return_type)
+    };
+    s += "  JNIEnv *env;\n";
+    s += "  int attached = 0;\n";
+    if callback.return_type != "void" {
+        s += "  jint result = 0;\n";
+    }
+    s += "  if ((*oc4j_glue_jvm)->GetEnv(oc4j_glue_jvm, (void **)&env, JNI_VERSION_1_6) != JNI_OK) {\n";
+    s += "    if ((*oc4j_glue_jvm)->AttachCurrentThread(oc4j_glue_jvm, (void **)&env, NULL) != JNI_OK) {\n";
+    s += &format!("      {}\n", return_statement);
+    s += "    }\n";
+    s += "    attached = 1;\n";
+    s += "  }\n";
+    // Once the COBOL program has thrown, skip the rest of the calls and let
+    // the exception reach the Java caller of the C function.
+    s += "  if (!(*env)->ExceptionCheck(env)) {\n";
+    s += "    jclass byte_array_class = (*env)->FindClass(env, \"[B\");\n";
+    s += &format!(
+        "    jobjectArray arguments = (*env)->NewObjectArray(env, {}, byte_array_class, NULL);\n",
+        callback.parameters.len()
+    );
+    for (index, p) in callback.parameters.iter().enumerate() {
+        s += &get_c_to_byte_array(p, &p.var_name, "    ");
+        s += &format!(
+            "    (*env)->SetObjectArrayElement(env, arguments, {}, jarg_{});\n",
+            index, p.var_name
+        );
     }
-    s += "    return 0;\n";
+    s += &format!(
+        "    {}oc4j_glue_call_program(env, \"{}\", arguments);\n",
+        if callback.return_type == "void" {
+            ""
+        } else {
+            "result = "
+        },
+        callback.program
+    );
+    for p in callback.parameters.iter() {
+        s += &get_c_from_byte_array(p, &p.var_name, "    ");
+    }
+    for p in callback.parameters.iter() {
+        s += &format!("    (*env)->DeleteLocalRef(env, jarg_{});\n", p.var_name);
+    }
+    s += "    (*env)->DeleteLocalRef(env, arguments);\n";
+    s += "    (*env)->DeleteLocalRef(env, byte_array_class);\n";
+    s += "  }\n";
+    s += "  if (attached) {\n";
+    s += "    (*oc4j_glue_jvm)->DetachCurrentThread(oc4j_glue_jvm);\n";
     s += "  }\n";
+    s += &format!("  {}\n", return_statement);
     s += "}\n";
     s
 }
 
-static C_LOCAL_PARAM_PREFIX: &'static str = "oc4j_glue_";
+fn get_c_error_condition(c_function: &CFunction) -> String {
+    let return_variable = format!("{}return", C_LOCAL_PARAM_PREFIX);
+    let mut conditions = Vec::new();
+    for error_value in c_function.error_values.iter() {
+        conditions.push(match error_value {
+            ErrorValue::Negative => format!("{} < 0", return_variable),
+            ErrorValue::Null => format!("{} == NULL", return_variable),
+            ErrorValue::Value(value) => format!("{} == {}", return_variable, value),
+        });
+    }
+    if conditions.is_empty() {
+        conditions.push(format!("{}errno != 0", C_LOCAL_PARAM_PREFIX));
+    }
+    conditions.join(" || ")
+}
+
+static C_HIDDEN_MACRO: &str = r#"#if defined(__GNUC__)
+#define OC4J_GLUE_HIDDEN __attribute__((visibility("hidden")))
+#else
+#define OC4J_GLUE_HIDDEN
+#endif
+"#;
+
+/// Resolve a C function from the library named by the `oc4j.c.glue.library` system property
+/// or the `OC4J_C_GLUE_LIBRARY` environment variable, or from the process itself if neither is set.
+/// A `CGlueException` is pending when NULL is returned.
+static C_DLSYM_FUNCTION: &str = r#"static void *oc4j_glue_library = NULL;
+static void *oc4j_glue_dlsym(JNIEnv *env, const char *name)
+{
+  char message[1024];
+  if (oc4j_glue_library == NULL) {
+    const char *path = getenv("OC4J_C_GLUE_LIBRARY");
+    const char *property_path = NULL;
+    jstring property = NULL;
+    jclass system = (*env)->FindClass(env, "java/lang/System");
+    if (system == NULL) {
+      return NULL;
+    }
+    jmethodID get_property = (*env)->GetStaticMethodID(env, system, "getProperty", "(Ljava/lang/String;)Ljava/lang/String;");
+    if (get_property == NULL) {
+      return NULL;
+    }
+    property = (jstring)(*env)->CallStaticObjectMethod(env, system, get_property, (*env)->NewStringUTF(env, "oc4j.c.glue.library"));
+    if ((*env)->ExceptionCheck(env)) {
+      return NULL;
+    }
+    if (property != NULL) {
+      property_path = (*env)->GetStringUTFChars(env, property, NULL);
+      path = property_path;
+    }
+    oc4j_glue_library = dlopen(path, RTLD_NOW | RTLD_GLOBAL);
+    if (oc4j_glue_library == NULL) {
+      snprintf(message, sizeof(message), "Unable to load C library: %s", dlerror());
+    }
+    if (property_path != NULL) {
+      (*env)->ReleaseStringUTFChars(env, property, property_path);
+    }
+    if (oc4j_glue_library == NULL) {
+      oc4j_glue_throw(env, message, 0, 0);
+      return NULL;
+    }
+  }
+  void *symbol = dlsym(oc4j_glue_library, name);
+  if (symbol == NULL) {
+    snprintf(message, sizeof(message), "C function not found: %s", name);
+    oc4j_glue_throw(env, message, 0, 0);
+  }
+  return symbol;
+}
+"#;
 
-fn get_c_file_content(c_function: &CFunction) -> String {
+/// The name of the native method implementation registered by `JNI_OnLoad`.
+fn get_c_native_name(c_function: &CFunction) -> String {
+    format!("{}native_{}", C_LOCAL_PARAM_PREFIX, c_function.name)
+}
+
+/// The JNI type of a parameter of the native method. A primitive that the C function
+/// may write through its pointer is passed in a one-element array.
+fn get_jni_parameter_type(param: &CParameter) -> &'static str {
+    match param.java_type {
+        PossibleJavaType::Byte if param.copies_back_value() => "jbyteArray",
+        PossibleJavaType::Short if param.copies_back_value() => "jshortArray",
+        PossibleJavaType::Int if param.copies_back_value() => "jintArray",
+        PossibleJavaType::Byte => "jbyte",
+        PossibleJavaType::Short => "jshort",
+        PossibleJavaType::Int => "jint",
+        PossibleJavaType::ByteArray | PossibleJavaType::CString => "jbyteArray",
+        PossibleJavaType::Handle => "jlong",
+        PossibleJavaType::HandleOut => "jlongArray",
+        PossibleJavaType::Callback => "",
+    }
+}
+
+/// The JNI element type of a primitive and the name in `Get<Name>ArrayRegion`.
+fn get_jni_array_element(java_type: &PossibleJavaType) -> (&'static str, &'static str) {
+    match java_type {
+        PossibleJavaType::Byte => ("jbyte", "Byte"),
+        PossibleJavaType::Short => ("jshort", "Short"),
+        _ => ("jint", "Int"),
+    }
+}
+
+/// The JNI type signature of the native method declared by `get_java_file_content`.
+fn get_jni_signature(c_function: &CFunction) -> String {
+    let mut s = "(".to_string();
+    for param in c_function.parameters.iter() {
+        if param.copies_back_value() {
+            s += "[";
+        }
+        s += match param.java_type {
+            PossibleJavaType::Byte => "B",
+            PossibleJavaType::Short => "S",
+            PossibleJavaType::Int => "I",
+            PossibleJavaType::ByteArray | PossibleJavaType::CString => "[B",
+            PossibleJavaType::Handle => "J",
+            PossibleJavaType::HandleOut => "[J",
+            PossibleJavaType::Callback => "",
+        };
+    }
+    s += if c_function.return_handle { ")J" } else { ")V" };
+    s
+}
+
+fn get_c_native_prototype(c_function: &CFunction) -> String {
+    let mut s = format!(
+        "{} JNICALL {}(JNIEnv *, jobject",
+        if c_function.return_handle {
+            "jlong"
+        } else {
+            "void"
+        },
+        get_c_native_name(c_function)
+    );
+    for param in c_function
+        .parameters
+        .iter()
+        .filter(|p| p.callback.is_none())
+    {
+        s += &format!(", {}", get_jni_parameter_type(param));
+    }
+    s += ")";
+    s
+}
+
+/// The `JNI_OnLoad` of the combined glue library, which registers the native method
+/// of every class so that neither the class names nor the C symbols have to follow
+/// the `Java_<class>_<method>` convention. A class that cannot be found or registered
+/// is reported on stderr and skipped, so that the classes the program does use still
+/// work; calling the native method of a skipped class throws `UnsatisfiedLinkError`.
+fn get_c_onload_file_content(c_functions: &[CFunction], java_package: Option<&str>) -> String {
+    let mut s = "".to_string();
+    s += "#include <stdio.h>\n";
+    s += "#include <jni.h>\n";
+    s += C_HIDDEN_MACRO;
+    for c_function in c_functions.iter() {
+        s += &format!(
+            "extern OC4J_GLUE_HIDDEN {};\n",
+            get_c_native_prototype(c_function)
+        );
+    }
+    // An unused static function is warned about.
+    if !c_functions.is_empty() {
+        s += "static void oc4j_glue_register(JNIEnv *env, const char *class_name, const JNINativeMethod *methods, jint count)\n";
+        s += "{\n";
+        s += "  jclass clazz = (*env)->FindClass(env, class_name);\n";
+        s += "  if (clazz == NULL) {\n";
+        s += "    (*env)->ExceptionClear(env);\n";
+        s += "    fprintf(stderr, \"oc4j-c-glue: class %s not found, its native method is not registered\\n\", class_name);\n";
+        s += "    return;\n";
+        s += "  }\n";
+        s += "  if ((*env)->RegisterNatives(env, clazz, methods, count) != JNI_OK) {\n";
+        s += "    (*env)->ExceptionClear(env);\n";
+        s += "    fprintf(stderr, \"oc4j-c-glue: unable to register the native method of %s\\n\", class_name);\n";
+        s += "  }\n";
+        s += "  (*env)->DeleteLocalRef(env, clazz);\n";
+        s += "}\n";
+    }
+    s += "JNIEXPORT jint JNICALL JNI_OnLoad(JavaVM *vm, void *reserved)\n";
+    s += "{\n";
+    s += "  JNIEnv *env;\n";
+    s += "  if ((*vm)->GetEnv(vm, (void **)&env, JNI_VERSION_1_6) != JNI_OK) {\n";
+    s += "    return JNI_ERR;\n";
+    s += "  }\n";
+    for c_function in c_functions.iter() {
+        s += &format!(
+            "  static const JNINativeMethod {}methods_{}[] = {{\n",
+            C_LOCAL_PARAM_PREFIX, c_function.name
+        );
+        s += &format!(
+            "    {{\"{}\", \"{}\", (void *){}}},\n",
+            c_function.name,
+            get_jni_signature(c_function),
+            get_c_native_name(c_function)
+        );
+        s += "  };\n";
+        s += &format!(
+            "  oc4j_glue_register(env, \"{}\", {}methods_{}, 1);\n",
+            get_jni_class_path(java_package, &c_function.name),
+            C_LOCAL_PARAM_PREFIX,
+            c_function.name
+        );
+    }
+    s += "  return JNI_VERSION_1_6;\n";
+    s += "}\n";
+    s
+}
+
+/// A function that calls a static inline function or a function-like macro,
+/// which have no symbol that the glue could declare `extern`.
+fn get_c_wrapper(c_function: &CFunction) -> String {
+    let parameters: Vec<String> = c_function
+        .parameters
+        .iter()
+        .map(|p| p.declaration())
+        .collect();
+    let arguments: Vec<&str> = c_function
+        .parameters
+        .iter()
+        .map(|p| p.var_name.as_str())
+        .collect();
+    let mut s = format!(
+        "{} {}({}) {{\n",
+        c_function.return_type,
+        c_function.wrapper_name(),
+        if parameters.is_empty() {
+            "void".to_string()
+        } else {
+            parameters.join(", ")
+        }
+    );
+    if c_function.returns_void() {
+        s += &format!("  {}({});\n", c_function.c_name(), arguments.join(", "));
+    } else {
+        s += &format!(
+            "  return {}({});\n",
+            c_function.c_name(),
+            arguments.join(", ")
+        );
+    }
+    s += "}\n";
+    s
+}
+
+fn get_c_file_content(
+    c_function: &CFunction,
+    bounds_policy: BoundsPolicy,
+    register_natives: bool,
+    dynamic_load: bool,
+    java_package: Option<&str>,
+) -> String {
+    // A wrapper is part of the glue, so it is called directly.
+    let dynamic_load = dynamic_load && !c_function.needs_wrapper();
     let mut s = "".to_string();
-    s += &format!("#include \"{}.h\"\n", c_function.name);
+    s += "#include <errno.h>\n";
+    s += "#include <stdint.h>\n";
+    s += "#include <stdio.h>\n";
+    s += "#include <string.h>\n";
+    if register_natives {
+        s += "#include <jni.h>\n";
+    } else {
+        s += &format!(
+            "#include \"{}\"\n",
+            get_jni_header_name(java_package, &c_function.name)
+        );
+    }
+    let has_strings = c_function
+        .parameters
+        .iter()
+        .any(|p| matches!(p.java_type, PossibleJavaType::CString));
+    if dynamic_load {
+        s += "#include <dlfcn.h>\n";
+    }
+    if dynamic_load || has_strings {
+        s += "#include <stdlib.h>\n";
+    }
     s += "#include \"custom.h\"\n";
+    s += &get_c_package_macro(java_package);
+    // Only emitted when something throws, since an unused static function is warned about.
+    let checks_bounds = matches!(bounds_policy, BoundsPolicy::Exception)
+        && c_function
+            .parameters
+            .iter()
+            .any(|p| matches!(p.java_type, PossibleJavaType::ByteArray));
+    if c_function.has_error_check() || dynamic_load || checks_bounds || has_strings {
+        s += C_THROW_FUNCTION;
+    }
 
     let num_of_params = c_function.parameters.len();
 
-    s += &format!("extern {} {}(", c_function.return_type, c_function.name);
-    for (index, param) in c_function.parameters.iter().enumerate() {
-        s += param.type_name.to_string().as_str();
-        if param.pointer_depth > 0 {
-            s += "*";
-        }
-        if index < num_of_params - 1 {
-            s += ", ";
+    // A variadic function is declared with its fixed parameters, so that the
+    // compiler promotes the variadic arguments as usual.
+    let mut parameter_types: Vec<String> = c_function.parameters
+        [..c_function.first_variadic_index().unwrap_or(num_of_params)]
+        .iter()
+        .map(|p| p.pointer_type_name())
+        .collect();
+    if c_function.variadic {
+        parameter_types.push("...".to_string());
+    }
+    if parameter_types.is_empty() {
+        parameter_types.push("void".to_string());
+    }
+    if c_function.needs_wrapper() {
+        s += &get_c_wrapper(c_function);
+    } else if dynamic_load {
+        s += C_DLSYM_FUNCTION;
+        s += &format!(
+            "static {} (*{}function)({}) = NULL;\n",
+            c_function.return_type,
+            C_LOCAL_PARAM_PREFIX,
+            parameter_types.join(", ")
+        );
+    } else {
+        s += &format!(
+            "extern {} {}({});\n",
+            c_function.return_type,
+            c_function.c_name(),
+            parameter_types.join(", ")
+        );
+    }
+
+    if c_function.has_callback() {
+        let callback_parameters: Vec<&CParameter> = c_function
+            .parameters
+            .iter()
+            .filter_map(|p| p.callback.as_ref())
+            .flat_map(|callback| callback.parameters.iter())
+            .collect();
+        s += &get_c_big_endian_functions(&callback_parameters);
+        s += C_CALLBACK_FUNCTIONS;
+        for param in c_function.parameters.iter() {
+            if let Some(callback) = &param.callback {
+                s += &get_c_trampoline(c_function, param, callback);
+            }
         }
     }
-    s += ");\n";
 
-    s += &format!(
-        "JNIEXPORT void JNICALL Java_{}_{}\n",
-        c_function.name, c_function.name
-    );
+    let (jni_return_type, c_return_statement) = if c_function.return_handle {
+        ("jlong", "return 0;")
+    } else {
+        ("void", "return;")
+    };
+    if register_natives {
+        s += C_HIDDEN_MACRO;
+        s += &format!(
+            "OC4J_GLUE_HIDDEN {} JNICALL {}\n",
+            jni_return_type,
+            get_c_native_name(c_function)
+        );
+    } else {
+        s += &format!(
+            "JNIEXPORT {} JNICALL {}\n",
+            jni_return_type,
+            get_jni_symbol(java_package, &c_function.name, &c_function.name)
+        );
+    }
     s += "(JNIEnv *env , jobject object";
 
+    for param in c_function
+        .parameters
+        .iter()
+        .filter(|p| p.callback.is_none())
+    {
+        s += &format!(", {} {}", get_jni_parameter_type(param), param.var_name);
+    }
+    s += ")\n{\n";
+    if c_function.has_callback() {
+        s += "  (*env)->GetJavaVM(env, &oc4j_glue_jvm);\n";
+    }
+    if dynamic_load {
+        s += &format!("  if ({}function == NULL) {{\n", C_LOCAL_PARAM_PREFIX);
+        s += &format!(
+            "    *(void **)&{}function = oc4j_glue_dlsym(env, \"{}\");\n",
+            C_LOCAL_PARAM_PREFIX,
+            c_function.c_name()
+        );
+        s += &format!("    if ({}function == NULL) {{\n", C_LOCAL_PARAM_PREFIX);
+        s += &format!("      {}\n", c_return_statement);
+        s += "    }\n";
+        s += "  }\n";
+    }
+
+    // Check the lengths of all arrays before acquiring any of them,
+    // so that nothing has to be released when an exception is thrown.
     for param in c_function.parameters.iter() {
-        match param.java_type {
-            PossibleJavaType::Byte => {
-                s += &format!(", jbyte {}", param.var_name);
-            }
-            PossibleJavaType::Short => {
-                s += &format!(", jshort {}", param.var_name);
-            }
-            PossibleJavaType::Int => {
-                s += &format!(", jint {}", param.var_name);
-            }
-            PossibleJavaType::ByteArray => {
-                s += &format!(", jbyteArray {}", param.var_name);
+        if let PossibleJavaType::ByteArray = param.java_type {
+            s += &format!(
+                "  jsize jlength_{} = (*env)->GetArrayLength(env, {});\n",
+                param.var_name, param.var_name
+            );
+            if let BoundsPolicy::Exception = bounds_policy {
+                s += &format!(
+                    "  if (jlength_{} < {}) {{\n",
+                    param.var_name, param.type_size
+                );
+                s += &format!(
+                    "    oc4j_glue_throw(env, \"{}: argument {} is shorter than {} bytes\", 0, 0);\n",
+                    c_function.name, param.var_name, param.type_size
+                );
+                s += &format!("    {}\n", c_return_statement);
+                s += "  }\n";
             }
+            s += &format!(
+                "  jsize jcopy_{} = jlength_{} < {} ? jlength_{} : {};\n",
+                param.var_name, param.var_name, param.type_size, param.var_name, param.type_size
+            );
+        }
+        if let PossibleJavaType::CString = param.java_type {
+            s += &format!(
+                "  jsize jlength_{} = (*env)->GetArrayLength(env, {});\n",
+                param.var_name, param.var_name
+            );
+            s += &format!(
+                "  jsize jcopy_{} = jlength_{} < {} ? jlength_{} : {};\n",
+                param.var_name,
+                param.var_name,
+                param.string_length,
+                param.var_name,
+                param.string_length
+            );
         }
     }
-    s += ")\n{\n";
+
+    // A string is copied to a zero-filled buffer with room for every character
+    // and the NUL after them, whether the text ends before or not.
+    let strings: Vec<&CParameter> = c_function
+        .parameters
+        .iter()
+        .filter(|p| matches!(p.java_type, PossibleJavaType::CString))
+        .collect();
+    for param in strings.iter() {
+        s += &format!(
+            "  char *{}{} = calloc({} + 1, 1);\n",
+            C_LOCAL_PARAM_PREFIX, param.var_name, param.string_length
+        );
+    }
+    if !strings.is_empty() {
+        let conditions: Vec<String> = strings
+            .iter()
+            .map(|p| format!("{}{} == NULL", C_LOCAL_PARAM_PREFIX, p.var_name))
+            .collect();
+        s += &format!("  if ({}) {{\n", conditions.join(" || "));
+        for param in strings.iter() {
+            s += &format!("    free({}{});\n", C_LOCAL_PARAM_PREFIX, param.var_name);
+        }
+        s += &format!(
+            "    oc4j_glue_throw(env, \"{}: unable to allocate a string argument\", 0, 0);\n",
+            c_function.name
+        );
+        s += &format!("    {}\n", c_return_statement);
+        s += "  }\n";
+    }
+
+    // The arrays acquired so far, which are released when a later one cannot be.
+    let mut acquired: Vec<&CParameter> = Vec::new();
     for param in c_function.parameters.iter() {
         match param.java_type {
-            PossibleJavaType::Byte => {
+            PossibleJavaType::Byte | PossibleJavaType::Short | PossibleJavaType::Int => {
+                let value = if param.copies_back_value() {
+                    let (jni_type, region) = get_jni_array_element(&param.java_type);
+                    s += &format!("  {} jvalue_{};\n", jni_type, param.var_name);
+                    s += &format!(
+                        "  (*env)->Get{}ArrayRegion(env, {}, 0, 1, &jvalue_{});\n",
+                        region, param.var_name, param.var_name
+                    );
+                    format!("jvalue_{}", param.var_name)
+                } else {
+                    param.var_name.to_string()
+                };
+                // Declared with its own type, e.g. an enum or `unsigned short`,
+                // since its address may be passed.
                 s += &format!(
-                    "  char {}{} = {};\n",
-                    C_LOCAL_PARAM_PREFIX, param.var_name, param.var_name
+                    "  {} {}{} = {};\n",
+                    param.type_name, C_LOCAL_PARAM_PREFIX, param.var_name, value
                 );
             }
-            PossibleJavaType::Short => {
+            PossibleJavaType::ByteArray => {
                 s += &format!(
-                    "  short {}{} = {};\n",
-                    C_LOCAL_PARAM_PREFIX, param.var_name, param.var_name
+                    "  jbyte* jbytes_{} = (*env)->GetByteArrayElements(env, {}, NULL);\n",
+                    param.var_name, param.var_name,
+                );
+                // NULL means the JVM has thrown OutOfMemoryError, which is left pending.
+                s += &format!("  if (jbytes_{} == NULL) {{\n", param.var_name);
+                for acquired_param in acquired.iter() {
+                    s += &format!(
+                        "    (*env)->ReleaseByteArrayElements(env, {}, jbytes_{}, JNI_ABORT);\n",
+                        acquired_param.var_name, acquired_param.var_name
+                    );
+                }
+                for string_param in strings.iter() {
+                    s += &format!(
+                        "    free({}{});\n",
+                        C_LOCAL_PARAM_PREFIX, string_param.var_name
+                    );
+                }
+                s += &format!("    {}\n", c_return_statement);
+                s += "  }\n";
+                acquired.push(param);
+                if param.is_array() {
+                    s += &format!(
+                        "  {} {}{}{}{};\n",
+                        param.type_name,
+                        "*".repeat(param.pointer_depth as usize - 1),
+                        C_LOCAL_PARAM_PREFIX,
+                        param.var_name,
+                        param.array_declarator_suffix()
+                    );
+                } else {
+                    s += &format!(
+                        "  {} {}{};\n",
+                        param.pointee_type_name(),
+                        C_LOCAL_PARAM_PREFIX,
+                        param.var_name,
+                    );
+                }
+                s += &format!(
+                    "  memset(&{}{}, 0, sizeof({}{}));\n",
+                    C_LOCAL_PARAM_PREFIX, param.var_name, C_LOCAL_PARAM_PREFIX, param.var_name,
+                );
+                s += &format!(
+                    "  memcpy(&{}{}, jbytes_{}, jcopy_{});\n",
+                    C_LOCAL_PARAM_PREFIX, param.var_name, param.var_name, param.var_name,
                 );
             }
-            PossibleJavaType::Int => {
+            PossibleJavaType::CString => {
                 s += &format!(
-                    "  int {}{} = {};\n",
-                    C_LOCAL_PARAM_PREFIX, param.var_name, param.var_name
+                    "  (*env)->GetByteArrayRegion(env, {}, 0, jcopy_{}, (jbyte *){}{});\n",
+                    param.var_name, param.var_name, C_LOCAL_PARAM_PREFIX, param.var_name
                 );
             }
-            PossibleJavaType::ByteArray => {
+            PossibleJavaType::Handle => {
                 s += &format!(
-                    "  jbyte* jbytes_{} = (*env)->GetByteArrayElements(env, {}, NULL);\n",
-                    param.var_name, param.var_name,
+                    "  {} {}{} = ({})(intptr_t){};\n",
+                    param.pointer_type_name(),
+                    C_LOCAL_PARAM_PREFIX,
+                    param.var_name,
+                    param.pointer_type_name(),
+                    param.var_name
                 );
+            }
+            PossibleJavaType::HandleOut => {
+                s += &format!(
+                    "  {}* {}{} = NULL;\n",
+                    param.qualified_type_name(),
+                    C_LOCAL_PARAM_PREFIX,
+                    param.var_name
+                );
+            }
+            PossibleJavaType::Callback => {}
+        }
+    }
+
+    if c_function.has_error_check() {
+        s += "  errno = 0;\n";
+    }
+    s += "  ";
+    // The result is kept even when it is not used, since the header may declare
+    // the function `warn_unused_result`, which a cast to void does not silence.
+    if !c_function.returns_void() {
+        s += &format!(
+            "{} {}return = ",
+            c_function.return_type, C_LOCAL_PARAM_PREFIX
+        );
+    }
+    if dynamic_load {
+        s += &format!("{}function(", C_LOCAL_PARAM_PREFIX);
+    } else if c_function.needs_wrapper() {
+        s += &format!("{}(", c_function.wrapper_name());
+    } else {
+        s += &format!("{}(", c_function.c_name());
+    }
+    for (index, param) in c_function.parameters.iter().enumerate() {
+        let take_address = match param.java_type {
+            PossibleJavaType::CString | PossibleJavaType::Handle | PossibleJavaType::Callback => {
+                false
+            }
+            PossibleJavaType::HandleOut => true,
+            // Arrays decay to a pointer to their first element.
+            _ => param.pointer_depth == 1 && !param.is_array(),
+        };
+        if take_address {
+            s += "&";
+        }
+        if param.callback.is_some() {
+            s += &CCallback::trampoline_name(&c_function.name, &param.var_name);
+        } else {
+            s += &format!("{}{}", C_LOCAL_PARAM_PREFIX, param.var_name);
+        }
+        if index < num_of_params - 1 {
+            s += ", ";
+        }
+    }
+    s += ");\n";
+    if !c_function.returns_void() && !c_function.uses_return_value() {
+        s += &format!("  (void){}return;\n", C_LOCAL_PARAM_PREFIX);
+    }
+    if c_function.has_error_check() {
+        s += &format!("  int {}errno = errno;\n", C_LOCAL_PARAM_PREFIX);
+    }
+
+    for param in c_function.parameters.iter() {
+        if let PossibleJavaType::ByteArray = param.java_type {
+            if param.copies_back() {
                 s += &format!(
-                    "  {} {}{};\n",
-                    param.type_name, C_LOCAL_PARAM_PREFIX, param.var_name,
+                    "  memcpy(jbytes_{}, &{}{}, jcopy_{});\n",
+                    param.var_name, C_LOCAL_PARAM_PREFIX, param.var_name, param.var_name,
                 );
+            }
+            s += &format!(
+                "  (*env)->ReleaseByteArrayElements(env, {}, jbytes_{}, {});\n",
+                param.var_name,
+                param.var_name,
+                if param.copies_back() {
+                    "0"
+                } else {
+                    "JNI_ABORT"
+                }
+            );
+        }
+        if param.copies_back_value() {
+            let (jni_type, region) = get_jni_array_element(&param.java_type);
+            s += &format!(
+                "  jvalue_{} = ({}){}{};\n",
+                param.var_name, jni_type, C_LOCAL_PARAM_PREFIX, param.var_name
+            );
+            s += &format!(
+                "  (*env)->Set{}ArrayRegion(env, {}, 0, 1, &jvalue_{});\n",
+                region, param.var_name, param.var_name
+            );
+        }
+        if let PossibleJavaType::CString = param.java_type {
+            if param.copies_back() {
                 s += &format!(
-                    "  memcpy(&{}{}, jbytes_{}, {});\n",
-                    C_LOCAL_PARAM_PREFIX, param.var_name, param.var_name, param.type_size,
+                    "  (*env)->SetByteArrayRegion(env, {}, 0, jcopy_{}, (const jbyte *){}{});\n",
+                    param.var_name, param.var_name, C_LOCAL_PARAM_PREFIX, param.var_name
                 );
             }
+            s += &format!("  free({}{});\n", C_LOCAL_PARAM_PREFIX, param.var_name);
+        }
+        if let PossibleJavaType::HandleOut = param.java_type {
+            s += &format!(
+                "  jlong jhandle_{} = (jlong)(intptr_t){}{};\n",
+                param.var_name, C_LOCAL_PARAM_PREFIX, param.var_name
+            );
+            s += &format!(
+                "  (*env)->SetLongArrayRegion(env, {}, 0, 1, &jhandle_{});\n",
+                param.var_name, param.var_name
+            );
         }
     }
 
-    s += &format!("  {}(", c_function.name);
-    for (index, param) in c_function.parameters.iter().enumerate() {
-        if param.pointer_depth == 1 {
-            s += "&";
-        }
-        s += &format!("{}{}", C_LOCAL_PARAM_PREFIX, param.var_name);
-        if index < num_of_params - 1 {
-            s += ", ";
+    if c_function.has_error_check() {
+        s += &format!("  if ({}) {{\n", get_c_error_condition(c_function));
+        s += "    char message[256];\n";
+        s += &format!(
+            "    snprintf(message, sizeof(message), \"{}: %s\", {}errno != 0 ? strerror({}errno) : \"error return value\");\n",
+            c_function.name, C_LOCAL_PARAM_PREFIX, C_LOCAL_PARAM_PREFIX
+        );
+        let return_value = if c_function.returns_void() {
+            "0".to_string()
+        } else if c_function.returns_pointer() {
+            format!("(jlong)(intptr_t){}return", C_LOCAL_PARAM_PREFIX)
+        } else {
+            format!("(jlong){}return", C_LOCAL_PARAM_PREFIX)
+        };
+        s += &format!(
+            "    oc4j_glue_throw(env, message, {}errno, {});\n",
+            C_LOCAL_PARAM_PREFIX, return_value
+        );
+        s += "  }\n";
+    }
+    if c_function.return_handle {
+        s += &format!(
+            "  return (jlong)(intptr_t){}return;\n",
+            C_LOCAL_PARAM_PREFIX
+        );
+    }
+    s += "}\n";
+    s
+}
+
+static C_JVM_FUNCTIONS: &str = r#"static JNIEnv *oc4j_glue_get_env(void)
+{
+  JavaVM *jvm;
+  JNIEnv *env;
+  jsize num_of_jvms = 0;
+  if (JNI_GetCreatedJavaVMs(&jvm, 1, &num_of_jvms) == JNI_OK && num_of_jvms > 0) {
+    if ((*jvm)->GetEnv(jvm, (void **)&env, JNI_VERSION_1_8) == JNI_OK) {
+      return env;
+    }
+    if ((*jvm)->AttachCurrentThread(jvm, (void **)&env, NULL) == JNI_OK) {
+      return env;
+    }
+    return NULL;
+  }
+  const char *class_path = getenv("OC4J_C_GLUE_CLASSPATH");
+  if (class_path == NULL) {
+    class_path = getenv("CLASSPATH");
+  }
+  char class_path_option[4096];
+  snprintf(class_path_option, sizeof(class_path_option), "-Djava.class.path=%s", class_path == NULL ? "." : class_path);
+  JavaVMOption options[1];
+  options[0].optionString = class_path_option;
+  JavaVMInitArgs vm_args;
+  vm_args.version = JNI_VERSION_1_8;
+  vm_args.nOptions = 1;
+  vm_args.options = options;
+  vm_args.ignoreUnrecognized = JNI_FALSE;
+  if (JNI_CreateJavaVM(&jvm, (void **)&env, &vm_args) != JNI_OK) {
+    return NULL;
+  }
+  return env;
+}
+"#;
+
+fn get_c_to_cobol_prototype(cobol_program: &CobolProgram) -> String {
+    let c_parameters: Vec<String> = cobol_program
+        .parameters
+        .iter()
+        .map(|p| format!("{} {}", p.pointer_type_name(), p.var_name))
+        .collect();
+    format!(
+        "int {}({})",
+        cobol_program.func_name,
+        if c_parameters.is_empty() {
+            "void".to_string()
+        } else {
+            c_parameters.join(", ")
         }
+    )
+}
+
+fn get_c_to_cobol_header_content(cobol_program: &CobolProgram) -> String {
+    let guard = format!("OC4J_GLUE_{}_H", cobol_program.func_name.to_uppercase());
+    let mut s = "".to_string();
+    s += &format!("#ifndef {}\n", guard);
+    s += &format!("#define {}\n", guard);
+    s += "#include \"custom.h\"\n";
+    s += &format!("{};\n", get_c_to_cobol_prototype(cobol_program));
+    s += "#endif\n";
+    s
+}
+
+/// A C function that runs an opensource COBOL 4J program and returns its RETURN-CODE,
+/// or -1 if the JVM or the program cannot be used.
+fn get_c_to_cobol_file_content(cobol_program: &CobolProgram) -> String {
+    let mut s = "".to_string();
+    s += "#include <stdio.h>\n";
+    s += "#include <stdlib.h>\n";
+    s += "#include <jni.h>\n";
+    s += &format!("#include \"{}.h\"\n", cobol_program.func_name);
+    s += &get_c_big_endian_functions(&cobol_program.parameters.iter().collect::<Vec<_>>());
+    s += C_JVM_FUNCTIONS;
+    s += &format!("{}\n{{\n", get_c_to_cobol_prototype(cobol_program));
+    s += "  JNIEnv *env = oc4j_glue_get_env();\n";
+    s += "  if (env == NULL) {\n";
+    s += "    return -1;\n";
+    s += "  }\n";
+    s += "  jclass storage_class = (*env)->FindClass(env, \"jp/osscons/opensourcecobol/libcobj/data/CobolDataStorage\");\n";
+    s += &format!(
+        "  jclass program_class = (*env)->FindClass(env, \"{}\");\n",
+        cobol_program.program_name.replace('.', "/")
+    );
+    s += "  if (storage_class == NULL || program_class == NULL) {\n";
+    s += "    (*env)->ExceptionDescribe(env);\n";
+    s += "    (*env)->ExceptionClear(env);\n";
+    s += "    return -1;\n";
+    s += "  }\n";
+    s += "  jmethodID storage_constructor = (*env)->GetMethodID(env, storage_class, \"<init>\", \"([B)V\");\n";
+    s += "  jmethodID program_constructor = (*env)->GetMethodID(env, program_class, \"<init>\", \"()V\");\n";
+    s += "  jmethodID run = (*env)->GetMethodID(env, program_class, \"run\", \"([Ljp/osscons/opensourcecobol/libcobj/data/CobolDataStorage;)I\");\n";
+    s += "  if (storage_constructor == NULL || program_constructor == NULL || run == NULL) {\n";
+    s += "    (*env)->ExceptionDescribe(env);\n";
+    s += "    (*env)->ExceptionClear(env);\n";
+    s += "    return -1;\n";
+    s += "  }\n";
+    s += &format!(
+        "  jobjectArray storages = (*env)->NewObjectArray(env, {}, storage_class, NULL);\n",
+        cobol_program.parameters.len()
+    );
+    for (index, p) in cobol_program.parameters.iter().enumerate() {
+        s += &get_c_to_byte_array(p, &p.var_name, "  ");
+        // CobolDataStorage(byte[]) shares the array, so the program writes into jarg_<name>.
+        s += &format!(
+            "  jobject storage_{} = (*env)->NewObject(env, storage_class, storage_constructor, jarg_{});\n",
+            p.var_name, p.var_name
+        );
+        s += &format!(
+            "  (*env)->SetObjectArrayElement(env, storages, {}, storage_{});\n",
+            index, p.var_name
+        );
     }
-    s += ");\n";
+    s += "  jobject program = (*env)->NewObject(env, program_class, program_constructor);\n";
+    s += "  jint result = -1;\n";
+    s += "  if (program != NULL) {\n";
+    s += "    result = (*env)->CallIntMethod(env, program, run, storages);\n";
+    s += "  }\n";
+    s += "  if ((*env)->ExceptionCheck(env)) {\n";
+    s += "    (*env)->ExceptionDescribe(env);\n";
+    s += "    (*env)->ExceptionClear(env);\n";
+    s += "    result = -1;\n";
+    s += "  }\n";
+    for p in cobol_program.parameters.iter() {
+        s += &get_c_from_byte_array(p, &p.var_name, "  ");
+    }
+    for p in cobol_program.parameters.iter() {
+        s += &format!("  (*env)->DeleteLocalRef(env, storage_{});\n", p.var_name);
+        s += &format!("  (*env)->DeleteLocalRef(env, jarg_{});\n", p.var_name);
+    }
+    s += "  (*env)->DeleteLocalRef(env, storages);\n";
+    s += "  if (program != NULL) {\n";
+    s += "    (*env)->DeleteLocalRef(env, program);\n";
+    s += "  }\n";
+    s += "  (*env)->DeleteLocalRef(env, program_class);\n";
+    s += "  (*env)->DeleteLocalRef(env, storage_class);\n";
+    s += "  return result;\n";
     s += "}\n";
     s
 }
@@ -427,8 +2937,50 @@ fn write_file(file: &mut File, content: String) -> Result<(), Box<std::io::Error
     Ok(())
 }
 
-fn read_c_functions_from_yml(rest: &Vec<String>) -> Result<Vec<CFunction>, GlueError> {
-    let yml_file_path = unwrap_some_or!(rest.get(0), return Err(GlueError::MissingFilePath));
+/// Everything a schema describes. A schema may leave out any of the lists,
+/// e.g. one for `generate_c_to_cobol` only has `programs`.
+struct Schema {
+    c_functions: Vec<CFunction>,
+    c_enums: Vec<CEnum>,
+    c_constants: Vec<CConstant>,
+    c_structs: Vec<CStruct>,
+    cobol_programs: Vec<CobolProgram>,
+}
+
+/// Convert each item of the list under `key` with `yml_to_item`.
+/// A missing or empty list has no items.
+fn yml_to_items<T>(yml: &Yaml, key: &str, yml_to_item: fn(&Yaml) -> Option<T>) -> Option<Vec<T>> {
+    let mut items = Vec::new();
+    let yml_items = match yml.as_hash()?.get(&Yaml::String(key.to_string())) {
+        Some(Yaml::Null) | None => return Some(items),
+        Some(yml_items) => yml_items.as_vec()?,
+    };
+    for yml_item in yml_items.iter() {
+        items.push(yml_to_item(yml_item)?);
+    }
+    Some(items)
+}
+
+fn yml_to_schema(yml: &Yaml) -> Option<Schema> {
+    let c_enums = yml_to_items(yml, "enums", yml_to_c_enum)?;
+    let mut c_functions: Vec<CFunction> = yml_to_items(yml, "functions", yml_to_c_functions)?
+        .into_iter()
+        .flatten()
+        .collect();
+    mark_enum_types(&mut c_functions, &c_enums);
+    let mut c_structs = yml_to_items(yml, "structs", yml_to_c_struct)?;
+    mark_enum_fields(&mut c_structs, &c_enums);
+    Some(Schema {
+        c_functions,
+        c_enums,
+        c_constants: yml_to_items(yml, "constants", yml_to_c_constant)?,
+        c_structs,
+        cobol_programs: yml_to_items(yml, "programs", yml_to_cobol_program)?,
+    })
+}
+
+fn read_schema(rest: &[String]) -> Result<Schema, GlueError> {
+    let yml_file_path = unwrap_some_or!(rest.first(), return Err(GlueError::MissingFilePath));
 
     let yml_content = unwrap_ok_or!(
         fs::read_to_string(yml_file_path),
@@ -441,18 +2993,301 @@ fn read_c_functions_from_yml(rest: &Vec<String>) -> Result<Vec<CFunction>, GlueE
         _,
         return Err(GlueError::InvalidYamlFormat(yml_file_path.to_string()))
     );
-    let c_functions = unwrap_some_or!(
-        yml_to_c_function(&yml_docs[0]),
+    let schema = unwrap_some_or!(
+        yml_docs.first().and_then(yml_to_schema),
         return Err(GlueError::InvalidYamlFormat(yml_file_path.to_string()))
     );
-    Ok(c_functions)
+    Ok(schema)
 }
 
-fn output_makefile(c_functions: &Vec<CFunction>, makefile_path: &str) {
-    let mut output_file = File::create(makefile_path).unwrap();
-    for c_function in c_functions.iter() {
-        write!(output_file, "{}\n", c_function.name);
+/// Write a Makefile that regenerates the glue from the schema and builds the JNI libraries.
+/// `glue_options` are passed on to `generate_java` and `generate_c`, and `link` lists the
+/// objects and libraries that provide the C functions.
+#[allow(clippy::too_many_arguments)]
+fn output_makefile(
+    c_functions: &[CFunction],
+    makefile_path: &str,
+    glue_options: &[String],
+    link: &[String],
+    combined_library: Option<&str>,
+    dynamic_load: bool,
+    has_constants: bool,
+    java_package: Option<&str>,
+) -> std::io::Result<()> {
+    let mut output_file = File::create(makefile_path)?;
+    let function_names: Vec<&str> = c_functions
+        .iter()
+        .filter(|f| !f.variadic)
+        .map(|f| f.name.as_str())
+        .collect();
+    let variadic_names: Vec<&str> = c_functions
+        .iter()
+        .filter(|f| f.variadic)
+        .map(|f| f.name.as_str())
+        .collect();
+    writeln!(output_file, "# Generated by cobj-c-glue parse_c.")?;
+    writeln!(output_file)?;
+    writeln!(output_file, "COBJ_C_GLUE ?= cobj-c-glue")?;
+    writeln!(output_file, "GLUE_OPTIONS = {}", glue_options.join(" "))?;
+    writeln!(output_file, "SCHEMA ?= function_schema.yml")?;
+    writeln!(output_file, "INFO_SOURCE ?= info.c")?;
+    writeln!(output_file, "CFLAGS ?= -O2")?;
+    writeln!(output_file, "JAVAC ?= javac")?;
+    writeln!(output_file, "JAVAC_FLAGS ?=")?;
+    writeln!(
+        output_file,
+        "JAVA_HOME ?= $(shell dirname $$(dirname $$(readlink -f $$(command -v $(JAVAC)))))"
+    )?;
+    writeln!(
+        output_file,
+        "JNI_PLATFORM ?= $(shell uname -s | tr '[:upper:]' '[:lower:]')"
+    )?;
+    writeln!(
+        output_file,
+        "JNI_INCLUDE ?= -I$(JAVA_HOME)/include -I$(JAVA_HOME)/include/$(JNI_PLATFORM)"
+    )?;
+    writeln!(output_file, "LIB_SUFFIX ?= .so")?;
+    if dynamic_load {
+        // The C library is opened at run time, so it must not be linked into the glue.
+        writeln!(output_file, "LINK ?=")?;
+        writeln!(output_file, "LDLIBS ?= -ldl")?;
+    } else {
+        writeln!(output_file, "LINK ?= {}", link.join(" "))?;
+        writeln!(output_file, "LDLIBS ?=")?;
+    }
+    writeln!(output_file)?;
+    if !variadic_names.is_empty() {
+        writeln!(
+            output_file,
+            "# The instances declared in the schema for the variadic functions {}.",
+            variadic_names.join(", ")
+        )?;
+        writeln!(output_file, "INSTANCES ?=")?;
+        writeln!(
+            output_file,
+            "FUNCTIONS = {} $(INSTANCES)",
+            function_names.join(" ")
+        )?;
+    } else {
+        writeln!(output_file, "FUNCTIONS = {}", function_names.join(" "))?;
+    }
+    let mut java_sources = vec!["$(FUNCTIONS:%=%.java)".to_string()];
+    if has_constants {
+        java_sources.push(format!("{}.java", JAVA_CONSTANTS_CLASS_NAME));
     }
+    if java_package.is_some() {
+        for (class_name, _) in JAVA_RUNTIME_CLASSES.iter() {
+            java_sources.push(format!("{}.java", class_name));
+        }
+    }
+    writeln!(output_file, "JAVA_SOURCES = {}", java_sources.join(" "))?;
+    writeln!(output_file, "C_SOURCES = $(FUNCTIONS:%=%.c)")?;
+    match combined_library {
+        Some(library) => writeln!(output_file, "LIBRARIES = lib{}$(LIB_SUFFIX)", library)?,
+        None => writeln!(output_file, "LIBRARIES = $(FUNCTIONS:%=lib%$(LIB_SUFFIX))")?,
+    }
+    writeln!(output_file)?;
+    writeln!(output_file, ".PHONY: all clean")?;
+    writeln!(output_file)?;
+    writeln!(output_file, "all: java.stamp $(LIBRARIES)")?;
+    writeln!(output_file)?;
+    writeln!(output_file, "$(SCHEMA): $(INFO_SOURCE)")?;
+    writeln!(output_file, "\t$(CC) $(INFO_SOURCE) -o info")?;
+    writeln!(output_file, "\t./info > $@")?;
+    writeln!(output_file)?;
+    writeln!(output_file, "java.stamp: $(SCHEMA)")?;
+    writeln!(
+        output_file,
+        "\t$(COBJ_C_GLUE) $(GLUE_OPTIONS) generate_java $(SCHEMA)"
+    )?;
+    writeln!(
+        output_file,
+        "\t$(JAVAC) $(JAVAC_FLAGS) -sourcepath . -h . $(JAVA_SOURCES)"
+    )?;
+    writeln!(output_file, "\ttouch $@")?;
+    writeln!(output_file)?;
+    writeln!(output_file, "c.stamp: $(SCHEMA)")?;
+    writeln!(
+        output_file,
+        "\t$(COBJ_C_GLUE) $(GLUE_OPTIONS) generate_c $(SCHEMA)"
+    )?;
+    writeln!(output_file, "\ttouch $@")?;
+    writeln!(output_file)?;
+    writeln!(output_file, "$(C_SOURCES): c.stamp ;")?;
+    writeln!(output_file)?;
+    match combined_library {
+        Some(library) => {
+            writeln!(output_file, "{}.c: c.stamp ;", library)?;
+            writeln!(output_file)?;
+            writeln!(
+                output_file,
+                "lib{}$(LIB_SUFFIX): {}.c $(C_SOURCES)",
+                library, library
+            )?;
+            writeln!(
+                output_file,
+                "\t$(CC) $(CFLAGS) $(JNI_INCLUDE) -shared -fPIC -o $@ $^ $(LINK) $(LDLIBS)"
+            )?;
+        }
+        None => {
+            // `javac -h` writes the header that each glue source includes.
+            writeln!(output_file, "lib%$(LIB_SUFFIX): %.c java.stamp")?;
+            writeln!(
+                output_file,
+                "\t$(CC) $(CFLAGS) $(JNI_INCLUDE) -shared -fPIC -o $@ $< $(LINK) $(LDLIBS)"
+            )?;
+        }
+    }
+    writeln!(output_file)?;
+    writeln!(output_file, "clean:")?;
+    write!(
+        output_file,
+        "\trm -f $(LIBRARIES) $(C_SOURCES) $(JAVA_SOURCES) $(FUNCTIONS:%={}) $(FUNCTIONS:%=%.class)",
+        get_jni_header_name(java_package, "%")
+    )?;
+    if let Some(library) = combined_library {
+        write!(output_file, " {}.c", library)?;
+    }
+    if has_constants {
+        write!(output_file, " {}.class", JAVA_CONSTANTS_CLASS_NAME)?;
+    }
+    writeln!(output_file, " java.stamp c.stamp info")?;
+    Ok(())
+}
+
+fn yaml_string(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+/// The CALL statements found by `scan_cobol`, in the YAML layout of the schema.
+fn cobol_calls_yaml(calls: &[CobolCall]) -> String {
+    let mut s = "calls:\n".to_string();
+    for call in calls.iter() {
+        s += &format!("  - program: {}\n", yaml_string(&call.program));
+        s += &format!("    file: {}\n", yaml_string(&call.file));
+        s += &format!("    line: {}\n", call.line);
+        if let Some(returning) = &call.returning {
+            s += &format!("    returning: {}\n", yaml_string(returning));
+        }
+        if call.arguments.is_empty() {
+            s += "    arguments: []\n";
+            continue;
+        }
+        s += "    arguments:\n";
+        for argument in call.arguments.iter() {
+            s += &format!("      - name: {}\n", yaml_string(&argument.name));
+            s += &format!("        mode: {}\n", argument.mode.name());
+        }
+    }
+    s
+}
+
+/// The programs called by `calls`, one per line, in the format of `--allowlist`.
+fn cobol_calls_allowlist(calls: &[CobolCall]) -> String {
+    let mut programs: Vec<&str> = Vec::new();
+    for call in calls.iter() {
+        if !programs.contains(&call.program.as_str()) {
+            programs.push(&call.program);
+        }
+    }
+    programs.iter().map(|p| format!("{}\n", p)).collect()
+}
+
+fn run_command(command: &mut Command) -> Result<(), GlueError> {
+    let program = command.get_program().to_string_lossy().to_string();
+    let status = unwrap_ok_or! {
+        command.status(),
+        _,
+        return Err(GlueError::Other(format!("Unable to run {}", program)))
+    };
+    if !status.success() {
+        return Err(GlueError::Other(format!("{} failed: {}", program, status)));
+    }
+    Ok(())
+}
+
+/// Compile the generated classes and bundle them with the glue libraries into one jar.
+/// The libraries are stored where `CobolRunnableCGlue.loadLibrary` looks for them.
+/// `-sourcepath` finds the classes of a package only in its directory, so the classes
+/// that `generate_java` wrote into the current directory for one are passed explicitly.
+fn package_jar(
+    schema: &Schema,
+    backend: JavaBackend,
+    java_package: Option<&str>,
+    library_names: &[String],
+    jar_path: &str,
+    classpath: Option<&str>,
+) -> Result<(), GlueError> {
+    let build_dir =
+        std::env::temp_dir().join(format!("cobj-c-glue-package-{}", std::process::id()));
+    let classes_dir = build_dir.join("classes");
+    let result = (|| {
+        let native_dir = classes_dir.join(format!(
+            "oc4j-c-glue/native/{}-{}",
+            std::env::consts::OS,
+            std::env::consts::ARCH
+        ));
+        unwrap_ok_or! {
+            fs::create_dir_all(&native_dir),
+            _,
+            return Err(GlueError::UnableToWriteFile(native_dir.display().to_string()))
+        };
+        for library_name in library_names.iter() {
+            let file_name = format!(
+                "{}{}{}",
+                std::env::consts::DLL_PREFIX,
+                library_name,
+                std::env::consts::DLL_SUFFIX
+            );
+            unwrap_ok_or! {
+                fs::copy(&file_name, native_dir.join(&file_name)),
+                _,
+                return Err(GlueError::UnableToReadFile(file_name))
+            };
+        }
+
+        let mut javac = Command::new("javac");
+        javac
+            .arg("-sourcepath")
+            .arg(".")
+            .arg("-d")
+            .arg(&classes_dir);
+        if let Some(classpath) = classpath {
+            javac.arg("-cp").arg(classpath);
+        }
+        for c_function in schema.c_functions.iter() {
+            javac.arg(format!("{}.java", c_function.name));
+        }
+        if !schema.c_constants.is_empty() {
+            javac.arg(format!("{}.java", JAVA_CONSTANTS_CLASS_NAME));
+        }
+        if java_package.is_some() {
+            for (class_name, _) in JAVA_RUNTIME_CLASSES.iter() {
+                javac.arg(format!("{}.java", class_name));
+            }
+            match backend {
+                JavaBackend::Jni => (),
+                JavaBackend::Ffm => {
+                    javac.arg(format!("{}.java", ffm::FFM_HELPER_CLASS_NAME));
+                }
+                JavaBackend::Jna => {
+                    javac.arg(format!("{}.java", jna::JNA_LIBRARY_NAME));
+                }
+            }
+        }
+        run_command(&mut javac)?;
+
+        run_command(
+            Command::new("jar")
+                .arg("cf")
+                .arg(jar_path)
+                .arg("-C")
+                .arg(&classes_dir)
+                .arg("."),
+        )
+    })();
+    let _ = fs::remove_dir_all(&build_dir);
+    result
 }
 
 fn main() -> Result<(), GlueError> {
@@ -460,6 +3295,25 @@ fn main() -> Result<(), GlueError> {
         synopsis "Generate glue code for C functions and opensource COBOL 4J";
         param mode:Option<String>, desc:"Specify running mode.";
         opt makefile_path:Option<String>, desc:"Specify the path of the generated Makefile.";
+        opt handle_types:Vec<String> = vec![], desc:"Specify a C type whose pointers are passed to COBOL as handles (parse_c).";
+        opt element_type:Vec<String> = vec![], desc:"Specify the type a void pointer points to, as function.parameter=type or function.callback.argument=type (parse_c).";
+        opt bounds_policy:Option<String>, desc:"Specify what the generated C does when a COBOL item is shorter than its C type (zero_fill or exception).";
+        opt backend:Option<String>, desc:"Specify how the generated Java calls C (jni, ffm or jna). ffm needs JDK 22 or later.";
+        opt register_natives:bool, desc:"Register the native methods from JNI_OnLoad of one combined glue library.";
+        opt dynamic_load:bool, desc:"Resolve the C functions with dlopen/dlsym when they are first called (generate_c).";
+        opt include:Vec<String> = vec![], desc:"Only generate glue for functions matching the glob (parse_c).";
+        opt exclude:Vec<String> = vec![], desc:"Do not generate glue for functions matching the glob (parse_c).";
+        opt allowlist:Option<String>, desc:"Specify a file listing the functions to generate glue for, one per line (parse_c).";
+        opt source_format:Option<String>, desc:"Specify the format of COBOL sources, fixed or free (scan_cobol, check).";
+        opt copy_path:Vec<String> = vec![], desc:"Specify a directory to search for copybooks (scan_cobol, check).";
+        opt scan_output:Option<String>, desc:"Specify the output of scan_cobol, yaml or allowlist.";
+        opt export_macro:Vec<String> = vec![], desc:"Specify an export or calling-convention macro that the header uses in declarations (parse_c).";
+        opt link:Vec<String> = vec![], desc:"Specify an object or library that the glue is linked with (parse_c).";
+        opt jar:Option<String>, desc:"Specify the path of the jar created by package (default: oc4j_c_glue.jar).";
+        opt classpath:Option<String>, desc:"Specify the classpath used to compile the generated classes (package).";
+        opt glue_library:Option<String>, desc:"Specify the name of the combined glue library (default: oc4j_c_glue).";
+        opt java_package:Option<String>, desc:"Specify the Java package of the generated classes, which also holds the runtime classes that generate_java then writes (generate_java, generate_c, package).";
+        opt cobol_prefix:Option<String>, desc:"Specify the prefix of the COBOL names of constant macros and anonymous enum constants (generate_cobol, default: C).";
     }.parse(),
     _,
     return Err(GlueError::InvalidCommandlineArguments)};
@@ -469,6 +3323,11 @@ fn main() -> Result<(), GlueError> {
             "parse_c" => RunningMode::ParseC,
             "generate_java" => RunningMode::GenerateJava,
             "generate_c" => RunningMode::GenerateC,
+            "generate_c_to_cobol" => RunningMode::GenerateCToCobol,
+            "generate_cobol" => RunningMode::GenerateCobol,
+            "package" => RunningMode::Package,
+            "scan_cobol" => RunningMode::ScanCobol,
+            "check" => RunningMode::Check,
             _ => {
                 return Err(GlueError::InvalidRunningMode(
                     "Invalid running mode".to_string(),
@@ -478,6 +3337,49 @@ fn main() -> Result<(), GlueError> {
         None => return Err(GlueError::MissingRunningMode),
     };
 
+    let bounds_policy = match args.bounds_policy.as_deref() {
+        None | Some("zero_fill") => BoundsPolicy::ZeroFill,
+        Some("exception") => BoundsPolicy::Exception,
+        Some(policy) => {
+            return Err(GlueError::Other(format!(
+                "Invalid bounds policy: {}",
+                policy
+            )))
+        }
+    };
+
+    let backend = match args.backend.as_deref() {
+        None | Some("jni") => JavaBackend::Jni,
+        Some("ffm") => JavaBackend::Ffm,
+        Some("jna") => JavaBackend::Jna,
+        Some(backend) => return Err(GlueError::Other(format!("Invalid backend: {}", backend))),
+    };
+
+    let source_format = match args.source_format.as_deref() {
+        None | Some("fixed") => SourceFormat::Fixed,
+        Some("free") => SourceFormat::Free,
+        Some(format) => {
+            return Err(GlueError::Other(format!(
+                "Invalid source format: {}",
+                format
+            )))
+        }
+    };
+
+    if let Some(java_package) = &args.java_package {
+        if !is_java_package_name(java_package) {
+            return Err(GlueError::Other(format!(
+                "Invalid Java package: {}",
+                java_package
+            )));
+        }
+    }
+
+    let glue_library = args
+        .glue_library
+        .unwrap_or_else(|| "oc4j_c_glue".to_string());
+    let cobol_prefix = args.cobol_prefix.unwrap_or_else(|| "C".to_string());
+
     match running_mode {
         RunningMode::ParseC => {
             let mut c_lang_parser = Parser::new();
@@ -487,54 +3389,530 @@ fn main() -> Result<(), GlueError> {
             _,
             return Err(GlueError::Other("Error loading C grammar".to_string()))};
 
-            let c_file_path = unwrap_some_or! {rest.get(0), return Err(GlueError::MissingFilePath)};
+            let c_file_path =
+                unwrap_some_or! {rest.first(), return Err(GlueError::MissingFilePath)};
             let source_code = unwrap_ok_or! {fs::read_to_string(c_file_path), _, return Err(GlueError::UnableToReadFile(c_file_path.to_string()))};
-            let c_functions = unwrap_some_or! {
+            let source_code = c_source::strip_vendor_extensions(&source_code, &args.export_macro);
+            let (mut c_functions, unsupported_functions) = unwrap_some_or! {
                 extract_function_declarators(&mut c_lang_parser, &source_code),
                 return Err(GlueError::InvalidCFormat(c_file_path.to_string()))
             };
-            println!("{}", c_info_source(&c_functions));
-            match args.makefile_path {
-                Some(s) => output_makefile(&c_functions, &s),
-                _ => output_makefile(&c_functions, "Makefile_output"),
+            let c_enums = unwrap_some_or! {
+                extract_enums(&mut c_lang_parser, &source_code),
+                return Err(GlueError::InvalidCFormat(c_file_path.to_string()))
+            };
+            let constants = extract_constants(&source_code, &c_enums);
+            let c_structs = unwrap_some_or! {
+                extract_structs(&mut c_lang_parser, &source_code),
+                return Err(GlueError::InvalidCFormat(c_file_path.to_string()))
+            };
+            let mut filter = FunctionFilter::new();
+            filter.include = args.include;
+            filter.exclude = args.exclude;
+            if let Some(allowlist_path) = args.allowlist {
+                let allowlist = unwrap_ok_or! {
+                    fs::read_to_string(&allowlist_path),
+                    _,
+                    return Err(GlueError::UnableToReadFile(allowlist_path))
+                };
+                filter.allowlist = FunctionFilter::parse_allowlist(&allowlist);
+            }
+            filter_functions(&mut c_functions, &filter);
+            for (name, reason) in unsupported_functions.iter() {
+                if filter.accepts(name) {
+                    eprintln!("Skipping {}: {}", name, reason);
+                }
+            }
+            mark_handle_types(&mut c_functions, &args.handle_types);
+            mark_enum_types(&mut c_functions, &c_enums);
+            mark_element_types(&mut c_functions, &args.element_type)?;
+            // `void` has no size, so the glue cannot copy what a `void *` points to.
+            c_functions.retain(|c_function| match find_void_pointer(c_function) {
+                Some(path) => {
+                    eprintln!(
+                        "Skipping {}: {} is a void pointer; give the type it points to with --element-type {}.{}=<type> or pass it as a handle with --handle-types void",
+                        c_function.name, path, c_function.name, path
+                    );
+                    false
+                }
+                None => true,
+            });
+            for c_function in c_functions.iter().filter(|f| f.variadic) {
+                eprintln!(
+                    "{} is variadic; declare its fixed-arity instances in the schema",
+                    c_function.name
+                );
+            }
+            for (name, parameters) in c_source::function_like_macros(&source_code) {
+                if filter.accepts(&name) && !c_functions.iter().any(|f| f.name == name) {
+                    eprintln!(
+                        "{}{} is a function-like macro; declare its signature in the schema with macro: true",
+                        name, parameters
+                    );
+                }
+            }
+            println!(
+                "{}",
+                c_info_source(&c_functions, &c_enums, &constants, &c_structs)
+            );
+            let makefile_path = args
+                .makefile_path
+                .unwrap_or_else(|| "Makefile_output".to_string());
+            let mut glue_options = Vec::new();
+            if let Some(policy) = &args.bounds_policy {
+                glue_options.push(format!("--bounds-policy {}", policy));
+            }
+            if args.register_natives {
+                glue_options.push("--register-natives".to_string());
+                glue_options.push(format!("--glue-library {}", glue_library));
+            }
+            if args.dynamic_load {
+                glue_options.push("--dynamic-load".to_string());
+            }
+            if let Some(java_package) = &args.java_package {
+                glue_options.push(format!("--java-package {}", java_package));
+            }
+            unwrap_ok_or! {
+                output_makefile(
+                    &c_functions,
+                    &makefile_path,
+                    &glue_options,
+                    &args.link,
+                    if args.register_natives {
+                        Some(&glue_library)
+                    } else {
+                        None
+                    },
+                    args.dynamic_load,
+                    !constants.is_empty(),
+                    args.java_package.as_deref(),
+                ),
+                _,
+                return Err(GlueError::UnableToWriteFile(makefile_path))
             };
         }
         RunningMode::GenerateJava => {
-            let c_functions = read_c_functions_from_yml(&rest)?;
+            let schema = read_schema(&rest)?;
+            let package_declaration = get_java_package_declaration(args.java_package.as_deref());
 
-            for c_function in c_functions.iter() {
-                let java_file_path = &format!("{}.java", c_function.name);
+            // Generate every class before writing any, so that a function
+            // the backend does not support leaves no partial output behind.
+            let mut java_files = Vec::new();
+            for c_function in schema.c_functions.iter() {
+                let java_file_content = match backend {
+                    JavaBackend::Jni => get_java_file_content(
+                        c_function,
+                        if args.register_natives {
+                            &glue_library
+                        } else {
+                            &c_function.name
+                        },
+                    ),
+                    JavaBackend::Ffm => unwrap_ok_or! {
+                        ffm::get_ffm_java_file_content(c_function, bounds_policy),
+                        e,
+                        return Err(GlueError::Other(format!("{}: {}", c_function.name, e)))
+                    },
+                    JavaBackend::Jna => unwrap_ok_or! {
+                        jna::get_jna_java_file_content(c_function, bounds_policy),
+                        e,
+                        return Err(GlueError::Other(format!("{}: {}", c_function.name, e)))
+                    },
+                };
+                java_files.push((
+                    format!("{}.java", c_function.name),
+                    package_declaration.clone() + &java_file_content,
+                ));
+            }
+            let library_file_content = match backend {
+                JavaBackend::Jna => unwrap_ok_or! {
+                    jna::get_jna_library_file_content(&schema.c_functions),
+                    e,
+                    return Err(GlueError::Other(e))
+                },
+                _ => "".to_string(),
+            };
+            for (java_file_path, java_file_content) in java_files {
                 let mut java_file = unwrap_ok_or! {
-                    File::create(java_file_path),
+                    File::create(&java_file_path),
                     _,
-                    return Err(GlueError::UnableToWriteFile(java_file_path.to_string()))
+                    return Err(GlueError::UnableToWriteFile(java_file_path))
                 };
-                let java_file_content = get_java_file_content(c_function);
                 unwrap_ok_or! {
                     write_file(&mut java_file, java_file_content),
                     _,
-                    return Err(GlueError::UnableToWriteFile(java_file_path.to_string()))
+                    return Err(GlueError::UnableToWriteFile(java_file_path))
+                };
+            }
+            if !schema.c_constants.is_empty() {
+                let constants_file_path = &format!("{}.java", JAVA_CONSTANTS_CLASS_NAME);
+                let mut constants_file = unwrap_ok_or! {
+                    File::create(constants_file_path),
+                    _,
+                    return Err(GlueError::UnableToWriteFile(constants_file_path.to_string()))
+                };
+                unwrap_ok_or! {
+                    write_file(&mut constants_file, package_declaration.clone() + &get_java_constants_file_content(&schema.c_constants)),
+                    _,
+                    return Err(GlueError::UnableToWriteFile(constants_file_path.to_string()))
+                };
+            }
+            if let JavaBackend::Ffm = backend {
+                let helper_file_path = &format!("{}.java", ffm::FFM_HELPER_CLASS_NAME);
+                let mut helper_file = unwrap_ok_or! {
+                    File::create(helper_file_path),
+                    _,
+                    return Err(GlueError::UnableToWriteFile(helper_file_path.to_string()))
+                };
+                unwrap_ok_or! {
+                    write_file(&mut helper_file, package_declaration.clone() + ffm::FFM_HELPER_CLASS),
+                    _,
+                    return Err(GlueError::UnableToWriteFile(helper_file_path.to_string()))
+                };
+            }
+            if let JavaBackend::Jna = backend {
+                let library_file_path = &format!("{}.java", jna::JNA_LIBRARY_NAME);
+                let mut library_file = unwrap_ok_or! {
+                    File::create(library_file_path),
+                    _,
+                    return Err(GlueError::UnableToWriteFile(library_file_path.to_string()))
+                };
+                unwrap_ok_or! {
+                    write_file(&mut library_file, package_declaration.clone() + &library_file_content),
+                    _,
+                    return Err(GlueError::UnableToWriteFile(library_file_path.to_string()))
                 };
             }
+            if args.java_package.is_some() {
+                for (class_name, class_content) in JAVA_RUNTIME_CLASSES.iter() {
+                    let runtime_file_path = &format!("{}.java", class_name);
+                    let mut runtime_file = unwrap_ok_or! {
+                        File::create(runtime_file_path),
+                        _,
+                        return Err(GlueError::UnableToWriteFile(runtime_file_path.to_string()))
+                    };
+                    unwrap_ok_or! {
+                        write_file(&mut runtime_file, package_declaration.clone() + class_content),
+                        _,
+                        return Err(GlueError::UnableToWriteFile(runtime_file_path.to_string()))
+                    };
+                }
+            }
         }
         RunningMode::GenerateC => {
-            let c_functions = read_c_functions_from_yml(&rest)?;
+            let schema = read_schema(&rest)?;
 
-            for c_function in c_functions.iter() {
+            for c_function in schema.c_functions.iter() {
                 let c_file_path = &format!("{}.c", c_function.name);
                 let mut c_file = unwrap_ok_or! {
                     File::create(c_file_path),
                     _,
                     return Err(GlueError::UnableToWriteFile(c_file_path.to_string()))
                 };
-                let java_file_content = get_c_file_content(c_function);
+                let java_file_content = get_c_file_content(
+                    c_function,
+                    bounds_policy,
+                    args.register_natives,
+                    args.dynamic_load,
+                    args.java_package.as_deref(),
+                );
                 unwrap_ok_or! {
                     write_file(&mut c_file, java_file_content),
                     _,
                     return Err(GlueError::UnableToWriteFile(c_file_path.to_string()))
                 };
             }
+            if args.register_natives {
+                let c_file_path = &format!("{}.c", glue_library);
+                let mut c_file = unwrap_ok_or! {
+                    File::create(c_file_path),
+                    _,
+                    return Err(GlueError::UnableToWriteFile(c_file_path.to_string()))
+                };
+                unwrap_ok_or! {
+                    write_file(&mut c_file, get_c_onload_file_content(&schema.c_functions, args.java_package.as_deref())),
+                    _,
+                    return Err(GlueError::UnableToWriteFile(c_file_path.to_string()))
+                };
+            }
+        }
+        RunningMode::GenerateCobol => {
+            let schema = read_schema(&rest)?;
+
+            if !schema.c_enums.is_empty() {
+                let copybook_path = cobol_wrapper::ENUM_COPYBOOK_PATH;
+                let mut copybook_file = unwrap_ok_or! {
+                    File::create(copybook_path),
+                    _,
+                    return Err(GlueError::UnableToWriteFile(copybook_path.to_string()))
+                };
+                unwrap_ok_or! {
+                    write_file(&mut copybook_file, cobol_wrapper::get_cobol_enum_copybook_content(&schema.c_enums, &cobol_prefix)),
+                    _,
+                    return Err(GlueError::UnableToWriteFile(copybook_path.to_string()))
+                };
+            }
+            if !schema.c_constants.is_empty() {
+                let copybook_path = cobol_wrapper::CONSTANT_COPYBOOK_PATH;
+                let mut copybook_file = unwrap_ok_or! {
+                    File::create(copybook_path),
+                    _,
+                    return Err(GlueError::UnableToWriteFile(copybook_path.to_string()))
+                };
+                unwrap_ok_or! {
+                    write_file(&mut copybook_file, cobol_wrapper::get_cobol_constant_copybook_content(&schema.c_constants, &cobol_prefix)),
+                    _,
+                    return Err(GlueError::UnableToWriteFile(copybook_path.to_string()))
+                };
+            }
+            if !schema.c_structs.is_empty() {
+                let copybook_path = cobol_wrapper::STRUCT_COPYBOOK_PATH;
+                let mut copybook_file = unwrap_ok_or! {
+                    File::create(copybook_path),
+                    _,
+                    return Err(GlueError::UnableToWriteFile(copybook_path.to_string()))
+                };
+                unwrap_ok_or! {
+                    write_file(&mut copybook_file, cobol_wrapper::get_cobol_struct_copybook_content(&schema.c_structs)),
+                    _,
+                    return Err(GlueError::UnableToWriteFile(copybook_path.to_string()))
+                };
+            }
+            if schema.c_structs.iter().any(|s| s.has_bitfields()) {
+                for (copybook_path, content) in [
+                    (
+                        cobol_wrapper::BITFIELD_COPYBOOK_PATH,
+                        cobol_wrapper::get_cobol_bitfield_copybook_content(&schema.c_structs),
+                    ),
+                    (
+                        cobol_wrapper::BITFIELD_ACCESSOR_COPYBOOK_PATH,
+                        cobol_wrapper::get_cobol_bitfield_accessor_copybook_content(
+                            &schema.c_structs,
+                        ),
+                    ),
+                ] {
+                    let mut copybook_file = unwrap_ok_or! {
+                        File::create(copybook_path),
+                        _,
+                        return Err(GlueError::UnableToWriteFile(copybook_path.to_string()))
+                    };
+                    unwrap_ok_or! {
+                        write_file(&mut copybook_file, content),
+                        _,
+                        return Err(GlueError::UnableToWriteFile(copybook_path.to_string()))
+                    };
+                }
+            }
+
+            for c_function in schema.c_functions.iter() {
+                let cobol_file_path =
+                    &format!("{}.cbl", cobol_wrapper::wrapper_program_name(c_function));
+                let mut cobol_file = unwrap_ok_or! {
+                    File::create(cobol_file_path),
+                    _,
+                    return Err(GlueError::UnableToWriteFile(cobol_file_path.to_string()))
+                };
+                unwrap_ok_or! {
+                    write_file(&mut cobol_file, cobol_wrapper::get_cobol_wrapper_file_content(c_function)),
+                    _,
+                    return Err(GlueError::UnableToWriteFile(cobol_file_path.to_string()))
+                };
+            }
+        }
+        RunningMode::Package => {
+            let schema = read_schema(&rest)?;
+            let library_names: Vec<String> = match backend {
+                JavaBackend::Jni if args.register_natives => vec![glue_library.clone()],
+                JavaBackend::Jni => schema.c_functions.iter().map(|f| f.name.clone()).collect(),
+                JavaBackend::Ffm | JavaBackend::Jna => Vec::new(),
+            };
+            let jar_path = args.jar.unwrap_or_else(|| "oc4j_c_glue.jar".to_string());
+            package_jar(
+                &schema,
+                backend,
+                args.java_package.as_deref(),
+                &library_names,
+                &jar_path,
+                args.classpath.as_deref(),
+            )?;
+        }
+        RunningMode::ScanCobol => {
+            if rest.is_empty() {
+                return Err(GlueError::MissingFilePath);
+            }
+            let mut calls = Vec::new();
+            for cobol_file_path in rest.iter() {
+                let tokens = unwrap_ok_or! {
+                    cobol_source::read_tokens(cobol_file_path, source_format, &args.copy_path),
+                    e,
+                    return Err(GlueError::Other(e))
+                };
+                calls.extend(cobol_call::scan_calls(&tokens));
+            }
+            match args.scan_output.as_deref() {
+                None | Some("yaml") => print!("{}", cobol_calls_yaml(&calls)),
+                Some("allowlist") => print!("{}", cobol_calls_allowlist(&calls)),
+                Some(output) => {
+                    return Err(GlueError::Other(format!("Invalid scan output: {}", output)))
+                }
+            }
+        }
+        RunningMode::Check => {
+            let schema = read_schema(&rest)?;
+            if rest.len() < 2 {
+                return Err(GlueError::MissingFilePath);
+            }
+            let mut num_of_errors = 0;
+            for cobol_file_path in rest[1..].iter() {
+                let tokens = unwrap_ok_or! {
+                    cobol_source::read_tokens(cobol_file_path, source_format, &args.copy_path),
+                    e,
+                    return Err(GlueError::Other(e))
+                };
+                for diagnostic in call_check::check_calls(&tokens, &schema.c_functions).iter() {
+                    eprintln!("{}", diagnostic);
+                    if diagnostic.severity == call_check::Severity::Error {
+                        num_of_errors += 1;
+                    }
+                }
+            }
+            if num_of_errors > 0 {
+                return Err(GlueError::Other(format!(
+                    "{} error(s) found in the CALL statements",
+                    num_of_errors
+                )));
+            }
+        }
+        RunningMode::GenerateCToCobol => {
+            let schema = read_schema(&rest)?;
+
+            for cobol_program in schema.cobol_programs.iter() {
+                let h_file_path = &format!("{}.h", cobol_program.func_name);
+                let mut h_file = unwrap_ok_or! {
+                    File::create(h_file_path),
+                    _,
+                    return Err(GlueError::UnableToWriteFile(h_file_path.to_string()))
+                };
+                unwrap_ok_or! {
+                    write_file(&mut h_file, get_c_to_cobol_header_content(cobol_program)),
+                    _,
+                    return Err(GlueError::UnableToWriteFile(h_file_path.to_string()))
+                };
+
+                let c_file_path = &format!("{}.c", cobol_program.func_name);
+                let mut c_file = unwrap_ok_or! {
+                    File::create(c_file_path),
+                    _,
+                    return Err(GlueError::UnableToWriteFile(c_file_path.to_string()))
+                };
+                unwrap_ok_or! {
+                    write_file(&mut c_file, get_c_to_cobol_file_content(cobol_program)),
+                    _,
+                    return Err(GlueError::UnableToWriteFile(c_file_path.to_string()))
+                };
+            }
         }
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `void fill(struct point *p)`, whose argument is copied through a byte array.
+    fn fill_function() -> CFunction {
+        let mut c_function = CFunction::new();
+        c_function.name = "fill".to_string();
+        c_function.return_type = "void".to_string();
+        c_function
+            .parameters
+            .push(CParameter::new("p", "struct point", 1, 8));
+        c_function.num_of_fixed_parameters = 1;
+        c_function
+    }
+
+    #[test]
+    fn zero_fill_copies_what_the_array_has() {
+        let s = get_c_file_content(&fill_function(), BoundsPolicy::ZeroFill, false, false, None);
+        assert!(s.contains("  jsize jcopy_p = jlength_p < 8 ? jlength_p : 8;\n"));
+        assert!(s.contains("  memset(&oc4j_glue_p, 0, sizeof(oc4j_glue_p));\n"));
+        assert!(s.contains("  memcpy(&oc4j_glue_p, jbytes_p, jcopy_p);\n"));
+        assert!(!s.contains("is shorter than"));
+        // Nothing throws, so the unused helper is left out.
+        assert!(!s.contains("oc4j_glue_throw"));
+    }
+
+    #[test]
+    fn exception_policy_throws_before_acquiring_arrays() {
+        let s = get_c_file_content(
+            &fill_function(),
+            BoundsPolicy::Exception,
+            false,
+            false,
+            None,
+        );
+        assert!(s.contains("static void oc4j_glue_throw("));
+        let check = s
+            .find("  if (jlength_p < 8) {\n    oc4j_glue_throw(env, \"fill: argument p is shorter than 8 bytes\", 0, 0);\n    return;\n  }\n")
+            .unwrap();
+        assert!(check < s.find("GetByteArrayElements").unwrap());
+    }
+
+    #[test]
+    fn jni_names_follow_the_package() {
+        assert!(is_java_package_name("com.example.glue_1"));
+        assert!(!is_java_package_name("com..example"));
+        assert!(!is_java_package_name("1com"));
+        assert!(!is_java_package_name("com.ex$"));
+        assert_eq!(get_jni_class_path(None, "fill"), "fill");
+        assert_eq!(
+            get_jni_class_path(Some("com.ex_a"), "fill"),
+            "com/ex_a/fill"
+        );
+        assert_eq!(
+            get_jni_header_name(Some("com.ex_a"), "my_f"),
+            "com_ex_a_my_f.h"
+        );
+        assert_eq!(
+            get_jni_symbol(Some("com.ex_a"), "my_f", "my_f"),
+            "Java_com_ex_1a_my_1f_my_1f"
+        );
+        assert_eq!(get_jni_symbol(None, "g_h", "g_h"), "Java_g_1h_g_1h");
+    }
+
+    #[test]
+    fn void_pointers_need_an_element_type() {
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_c::language()).unwrap();
+        let (mut c_functions, _) = extract_function_declarators(
+            &mut parser,
+            "void each(int n, void (*f)(int v));\nvoid fill(void *buf, int n);\n",
+        )
+        .unwrap();
+        assert_eq!(find_void_pointer(&c_functions[0]), None);
+        assert_eq!(find_void_pointer(&c_functions[1]), Some("buf".to_string()));
+        mark_element_types(&mut c_functions, &["fill.buf=char".to_string()]).unwrap();
+        assert_eq!(find_void_pointer(&c_functions[1]), None);
+    }
+
+    #[test]
+    fn unsupported_prototypes_are_skipped() {
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_c::language()).unwrap();
+        let (c_functions, unsupported) = extract_function_declarators(
+            &mut parser,
+            "int ok(int a);\nint deep(char ***p);\nshort ok2(short b);\n",
+        )
+        .unwrap();
+        let names: Vec<&str> = c_functions.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["ok", "ok2"]);
+        assert_eq!(
+            unsupported,
+            vec![(
+                "deep".to_string(),
+                "parameter 1 has more than two levels of pointers".to_string()
+            )]
+        );
+    }
+}